@@ -0,0 +1,531 @@
+extern crate proc_macro;
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    parse2, Fields, File, FnArg, GenericArgument, Item, ItemFn, ItemStruct, Lifetime, Lit, Meta,
+    NestedMeta, Pat, PathArguments, ReturnType, Type, TypeParamBound,
+};
+
+/// Report an error with the given `span` and message.
+fn spanned_err(span: Span, msg: impl Into<String>) -> TokenStream {
+    syn::Error::new(span, msg.into()).to_compile_error()
+}
+
+/// Finds the part of `ty` (if any) that can't be the type of a field in the
+/// probe struct or of a local: references and anything else with a lifetime
+/// other than `'static` (the struct doesn't have any lifetime parameters) and
+/// `impl Trait`s.
+fn unstorable(ty: &Type) -> Option<Span> {
+    let borrowed = |l: &Lifetime| {
+        if l.ident == "static" {
+            None
+        } else {
+            Some(l.span())
+        }
+    };
+
+    match ty {
+        Type::Reference(r) => match &r.lifetime {
+            Some(l) if l.ident == "static" => unstorable(&r.elem),
+            _ => Some(r.span()),
+        },
+        Type::ImplTrait(i) => Some(i.span()),
+        Type::Array(a) => unstorable(&a.elem),
+        Type::Slice(s) => unstorable(&s.elem),
+        Type::Ptr(p) => unstorable(&p.elem),
+        Type::Paren(p) => unstorable(&p.elem),
+        Type::Group(g) => unstorable(&g.elem),
+        Type::Tuple(t) => t.elems.iter().find_map(unstorable),
+        Type::TraitObject(t) => t.bounds.iter().find_map(|b| match b {
+            TypeParamBound::Lifetime(l) => borrowed(l),
+            TypeParamBound::Trait(_) => None,
+        }),
+        Type::Path(p) => p.qself.iter().find_map(|q| unstorable(&q.ty)).or_else(|| {
+            p.path.segments.iter().find_map(|seg| match &seg.arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter().find_map(|a| match a {
+                    GenericArgument::Lifetime(l) => borrowed(l),
+                    GenericArgument::Type(t) => unstorable(t),
+                    _ => None,
+                }),
+                _ => None,
+            })
+        }),
+        _ => None,
+    }
+}
+
+/// Errors if `ty` can't be stored in the probe struct.
+fn check_storable(ty: &Type, what: &str) -> Result<(), TokenStream> {
+    match unstorable(ty) {
+        Some(span) => Err(spanned_err(
+            span,
+            format!(
+                "Block {} are stored in the probe struct so they can't borrow anything (other than \
+                `'static`s) or be `impl Trait`s.",
+                what,
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// A field of the probe struct.
+struct Field {
+    name: Ident,
+    ty: Type,
+    doc: String,
+}
+
+/// A `#[block("tag")]` function, along with the fields it gets in the probe
+/// struct.
+struct Block {
+    func: ItemFn,
+    inputs: Vec<(Ident, Field)>,
+    output: Option<Field>,
+}
+
+/// Pulls the tag out of `#[block("tag")]`, if `func` has that attribute (and
+/// removes the attribute).
+fn take_tag(func: &mut ItemFn) -> Result<Option<String>, TokenStream> {
+    let pos = match func.attrs.iter().position(|a| a.path.is_ident("block")) {
+        Some(pos) => pos,
+        None => return Ok(None),
+    };
+    let attr = func.attrs.remove(pos);
+
+    let err = || {
+        spanned_err(
+            attr.span(),
+            "We expected a tag, like this: `#[block(\"tag-name\")]`.",
+        )
+    };
+
+    let tag = match attr.parse_meta() {
+        Ok(Meta::List(list)) if list.nested.len() == 1 => match list.nested.first() {
+            Some(NestedMeta::Lit(Lit::Str(s))) => s.value(),
+            _ => return Err(err()),
+        },
+        _ => return Err(err()),
+    };
+
+    // Tags become field name prefixes so they have to be identifiers once
+    // dashes are swapped for underscores.
+    let prefix = tag.replace('-', "_");
+    let valid = prefix.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if valid {
+        Ok(Some(prefix))
+    } else {
+        Err(spanned_err(
+            attr.span(),
+            format!(
+                "`{}` can't be used as a tag; tags are used as the prefix for field names.",
+                tag
+            ),
+        ))
+    }
+}
+
+/// Whether `s` is a (non-generic) unit struct, i.e. `struct Probes;`.
+fn is_unit(s: &ItemStruct) -> bool {
+    match s.fields {
+        Fields::Unit => s.generics.params.is_empty(),
+        _ => false,
+    }
+}
+
+/// Whether `s` has a `#[probes]` attribute.
+fn is_marked(s: &ItemStruct) -> bool {
+    s.attrs.iter().any(|a| a.path.is_ident("probes"))
+}
+
+/// Picks the struct that holds the probes out of the unit structs in the
+/// macro's input: the one marked `#[probes]` (which loses the attribute) or,
+/// if none are marked, the only one. The others go into `rest`.
+fn probe_struct(
+    mut units: Vec<ItemStruct>,
+    rest: &mut Vec<Item>,
+) -> Result<ItemStruct, TokenStream> {
+    let marked: Vec<usize> = (0..units.len()).filter(|i| is_marked(&units[*i])).collect();
+
+    let idx = match (marked.as_slice(), units.len()) {
+        ([], 0) => {
+            return Err(spanned_err(
+                Span::call_site(),
+                "We expected a unit struct (i.e. `struct Probes;`) to hold the probes.",
+            ));
+        }
+        ([], 1) => 0,
+        ([], _) => {
+            return Err(spanned_err(
+                units[1].span(),
+                "There's more than one unit struct; mark the probe struct with `#[probes]`.",
+            ));
+        }
+        ([i], _) => *i,
+        ([_, i, ..], _) => {
+            return Err(spanned_err(
+                units[*i].span(),
+                "Only one struct can be marked `#[probes]`.",
+            ))
+        }
+    };
+
+    let mut probes = units.remove(idx);
+    probes.attrs.retain(|a| !a.path.is_ident("probes"));
+    rest.extend(units.into_iter().map(Item::Struct));
+
+    Ok(probes)
+}
+
+fn block(mut func: ItemFn) -> Result<Result<Block, ItemFn>, TokenStream> {
+    let prefix = match take_tag(&mut func)? {
+        Some(prefix) => prefix,
+        None => return Ok(Err(func)),
+    };
+    let name = func.sig.ident.to_string();
+
+    if !func.sig.generics.params.is_empty() {
+        return Err(spanned_err(
+            func.sig.generics.span(),
+            "Blocks can't be generic.",
+        ));
+    }
+
+    let mut inputs = Vec::new();
+    for arg in func.sig.inputs.iter() {
+        let arg = match arg {
+            FnArg::Typed(arg) => arg,
+            FnArg::Receiver(r) => {
+                return Err(spanned_err(r.span(), "Blocks have to be free functions."))
+            }
+        };
+
+        let ident = match &*arg.pat {
+            Pat::Ident(p) => p.ident.clone(),
+            p => {
+                return Err(spanned_err(
+                    p.span(),
+                    "Block arguments have to be plain identifiers.",
+                ))
+            }
+        };
+
+        check_storable(&arg.ty, "arguments")?;
+
+        let field = Field {
+            name: Ident::new(&format!("{}_{}", prefix, ident), ident.span()),
+            ty: (*arg.ty).clone(),
+            doc: format!(
+                "`{}`, as passed to `{}` (the `{}` block).",
+                ident, name, prefix
+            ),
+        };
+        inputs.push((ident, field));
+    }
+
+    let output = match &func.sig.output {
+        ReturnType::Default => None,
+        ReturnType::Type(_, ty) => {
+            check_storable(ty, "return types")?;
+
+            Some(Field {
+                name: Ident::new(&format!("{}_out", prefix), ty.span()),
+                ty: (**ty).clone(),
+                doc: format!("What `{}` (the `{}` block) returned.", name, prefix),
+            })
+        }
+    };
+
+    Ok(Ok(Block {
+        func,
+        inputs,
+        output,
+    }))
+}
+
+/// Wraps the body of a block so that it records its inputs and output in the
+/// probe struct (if there's a recording in progress).
+#[cfg(feature = "probe")]
+fn instrument(probes: &Ident, block: &mut Block) {
+    let (some, clone) = (
+        quote! { ::core::option::Option::Some },
+        quote! { ::core::clone::Clone::clone },
+    );
+
+    let record_inputs = block.inputs.iter().map(|(arg, field)| {
+        let name = &field.name;
+        quote! { __probes.#name = #some(#clone(&#arg)); }
+    });
+    let record_output = block.output.as_ref().map(|field| {
+        let name = &field.name;
+        quote! { #probes::__record(|__probes| __probes.#name = #some(#clone(&out))); }
+    });
+
+    // The original body goes in a closure so that `return`s in it still work.
+    let body = &block.func.block;
+    let ret = match &block.func.sig.output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) => quote! { #ty },
+    };
+
+    *block.func.block = syn::parse_quote! {{
+        #probes::__record(|__probes| { #(#record_inputs)* });
+        let out: #ret = (move || #body)();
+        #record_output
+        out
+    }};
+}
+
+#[cfg(not(feature = "probe"))]
+fn instrument(_probes: &Ident, _block: &mut Block) {}
+
+/// The probe struct's inherent methods.
+#[cfg(feature = "probe")]
+fn probe_methods(probes: &ItemStruct) -> TokenStream {
+    let name = &probes.ident;
+    let vis = &probes.vis;
+
+    quote! {
+        impl #name {
+            /// Runs `f`, returning what it returned along with the inputs and
+            /// outputs of every block it called. If a block was called more
+            /// than once, the last call wins.
+            #vis fn record<R>(f: impl ::core::ops::FnOnce() -> R) -> (R, Self) {
+                let prev = Self::__with(|c| {
+                    c.replace(::core::option::Option::Some(::core::default::Default::default()))
+                });
+
+                let ret = f();
+                let rec = Self::__with(|c| c.replace(prev)).unwrap_or_default();
+
+                (ret, rec)
+            }
+
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            fn __record(f: impl ::core::ops::FnOnce(&mut Self)) {
+                Self::__with(|c| {
+                    if let ::core::option::Option::Some(p) = c.borrow_mut().as_mut() {
+                        f(p)
+                    }
+                })
+            }
+
+            fn __with<R>(
+                f: impl ::core::ops::FnOnce(
+                    &::core::cell::RefCell<::core::option::Option<Self>>,
+                ) -> R,
+            ) -> R {
+                // `std` isn't in scope in `no_std` crates; recording needs it
+                // (for the thread local) either way.
+                extern crate std;
+
+                std::thread_local! {
+                    static RECORDING: ::core::cell::RefCell<::core::option::Option<#name>> =
+                        ::core::cell::RefCell::new(::core::option::Option::None);
+                }
+
+                RECORDING.with(f)
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "probe"))]
+fn probe_methods(probes: &ItemStruct) -> TokenStream {
+    let name = &probes.ident;
+    let vis = &probes.vis;
+
+    quote! {
+        impl #name {
+            /// Runs `f` and returns what it returned along with an empty
+            /// recording (the `probe` feature is off so blocks aren't
+            /// recorded).
+            #vis fn record<R>(f: impl ::core::ops::FnOnce() -> R) -> (R, Self) {
+                (f(), ::core::default::Default::default())
+            }
+        }
+    }
+}
+
+/// Use like:
+///
+/// ```ignore
+/// design! {
+///     /// The inputs and outputs of every block.
+///     pub struct Probes;
+///
+///     #[block("alu")]
+///     fn alu(a: u16, b: u16) -> u16 { a + b }
+///
+///     #[block("next-pc")]
+///     fn next_pc(pc: u16) -> u16 { alu(pc, 1) }
+/// }
+///
+/// let (pc, probes) = Probes::record(|| next_pc(0x3000));
+/// let Probes { alu_a, alu_b, alu_out, next_pc_pc, next_pc_out } = probes;
+/// ```
+///
+/// The block must contain a unit struct to hold the probes: either the only
+/// unit struct in the block or the one marked `#[probes]` (other unit structs
+/// are left alone). It's filled in with a field for every argument and the
+/// return value of every function that has a `#[block("tag")]` attribute.
+/// Fields are named `<tag>_<argument>` and `<tag>_out` (with any dashes in the
+/// tag turned into underscores) and hold `Option`s of the argument/return
+/// types, which have to be `Clone` and `Debug`.
+///
+/// Everything else in the block (other items, functions without a `#[block]`
+/// attribute) is passed through untouched.
+///
+/// Block arguments and return types are stored in the probe struct, so they
+/// can't be references (or have any other lifetimes), other than `'static`
+/// ones, or `impl Trait`s.
+///
+/// With the `probe` feature on, calls to blocks within a call to
+/// `<struct>::record` are recorded; this needs `std` (which is linked in even
+/// if the crate using `design!` is `no_std`). Without it, blocks are left alone
+/// and `record` always returns an empty struct.
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate design_macros;
+/// design! {
+///     struct Probes;
+///
+///     #[block]
+///     fn missing_tag(a: u8) -> u8 { a }
+/// }
+/// ```
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate design_macros;
+/// design! {
+///     struct Probes {}
+///
+///     #[block("not-a-unit-struct")]
+///     fn not_a_unit_struct(a: u8) -> u8 { a }
+/// }
+/// ```
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate design_macros;
+/// design! {
+///     struct Probes;
+///
+///     #[block("borrowed")]
+///     fn borrowed(a: &[u8]) -> u8 { a[0] }
+/// }
+/// ```
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate design_macros;
+/// design! {
+///     struct Probes;
+///
+///     #[block("opaque")]
+///     fn opaque(a: u8) -> impl Copy { a }
+/// }
+/// ```
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate design_macros;
+/// design! {
+///     #[block("no-struct")]
+///     fn no_struct(a: u8) -> u8 { a }
+/// }
+/// ```
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate design_macros;
+/// design! {
+///     struct Probes;
+///     struct Marker;
+///
+///     #[block("unmarked")]
+///     fn unmarked(a: u8) -> u8 { a }
+/// }
+/// ```
+#[proc_macro]
+pub fn design(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    match design_inner(TokenStream::from(input)) {
+        Ok(ts) | Err(ts) => ts.into(),
+    }
+}
+
+fn design_inner(input: TokenStream) -> Result<TokenStream, TokenStream> {
+    let file: File = parse2(input).map_err(|e| e.to_compile_error())?;
+
+    let mut units: Vec<ItemStruct> = Vec::new();
+    let mut blocks = Vec::new();
+    let mut rest = Vec::new();
+
+    for item in file.items {
+        match item {
+            Item::Struct(s) if is_unit(&s) => units.push(s),
+            Item::Struct(s) if is_marked(&s) => {
+                return Err(spanned_err(
+                    s.span(),
+                    "The probe struct has to be a unit struct (i.e. `struct Probes;`).",
+                ));
+            }
+            Item::Fn(f) => match block(f)? {
+                Ok(b) => blocks.push(b),
+                Err(f) => rest.push(Item::Fn(f)),
+            },
+            item => rest.push(item),
+        }
+    }
+
+    let probes = probe_struct(units, &mut rest)?;
+
+    let fields: Vec<&Field> = blocks
+        .iter()
+        .flat_map(|b| b.inputs.iter().map(|(_, f)| f).chain(b.output.iter()))
+        .collect();
+
+    let mut seen = std::collections::HashSet::new();
+    for f in fields.iter() {
+        if !seen.insert(f.name.to_string()) {
+            return Err(spanned_err(
+                f.name.span(),
+                format!("There's more than one probe named `{}`.", f.name),
+            ));
+        }
+    }
+
+    let (attrs, vis, name) = (&probes.attrs, &probes.vis, &probes.ident);
+    let (field_names, field_tys, field_docs) = (
+        fields.iter().map(|f| &f.name),
+        fields.iter().map(|f| &f.ty),
+        fields.iter().map(|f| &f.doc),
+    );
+
+    let strukt = quote! {
+        #(#attrs)*
+        #[derive(Clone, Debug, Default)]
+        #vis struct #name {
+            #(
+                #[doc = #field_docs]
+                pub #field_names: ::core::option::Option<#field_tys>,
+            )*
+        }
+    };
+    let methods = probe_methods(&probes);
+
+    for b in blocks.iter_mut() {
+        instrument(name, b);
+    }
+    let funcs = blocks.iter().map(|b| &b.func);
+
+    Ok(quote! {
+        #strukt
+        #methods
+        #(#funcs)*
+        #(#rest)*
+    })
+}
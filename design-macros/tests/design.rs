@@ -0,0 +1,115 @@
+#[macro_use(design)]
+extern crate design_macros;
+
+design! {
+    /// Everything the blocks below saw.
+    pub struct Probes;
+
+    #[block("alu")]
+    fn alu(a: u16, b: u16) -> u16 {
+        if b == 0 {
+            return a;
+        }
+
+        a.wrapping_add(b)
+    }
+
+    #[block("next-pc")]
+    fn next_pc(pc: u16, branch: bool) -> u16 {
+        let off = if branch { offset() } else { 1 };
+        alu(pc, off)
+    }
+
+    // Not a block; passed through untouched.
+    fn offset() -> u16 {
+        0x10
+    }
+}
+
+#[test]
+fn blocks_still_work() {
+    assert_eq!(next_pc(0x3000, false), 0x3001);
+    assert_eq!(alu(7, 0), 7);
+}
+
+#[test]
+fn fields() {
+    let (pc, probes) = Probes::record(|| next_pc(0x3000, true));
+    assert_eq!(pc, 0x3010);
+
+    // Adding a signal to a block breaks this destructure.
+    let Probes {
+        alu_a,
+        alu_b,
+        alu_out,
+        next_pc_pc,
+        next_pc_branch,
+        next_pc_out,
+    } = probes;
+
+    if cfg!(feature = "probe") {
+        assert_eq!(
+            (alu_a, alu_b, alu_out),
+            (Some(0x3000), Some(0x10), Some(0x3010))
+        );
+        assert_eq!(
+            (next_pc_pc, next_pc_branch, next_pc_out),
+            (Some(0x3000), Some(true), Some(0x3010))
+        );
+    } else {
+        assert_eq!((alu_a, alu_b, alu_out), (None, None, None));
+        assert_eq!(
+            (next_pc_pc, next_pc_branch, next_pc_out),
+            (None, None, None)
+        );
+    }
+}
+
+#[test]
+fn only_inside_record() {
+    let _ = alu(1, 2);
+    let (_, probes) = Probes::record(|| ());
+
+    assert_eq!(probes.alu_out, None);
+}
+
+#[test]
+#[cfg(feature = "probe")]
+fn last_call_wins() {
+    let (_, probes) = Probes::record(|| {
+        let _ = alu(1, 2);
+        alu(3, 4)
+    });
+
+    assert_eq!(probes.alu_out, Some(7));
+}
+
+mod marked {
+    design! {
+        /// Not the probe struct.
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct Marker;
+
+        #[probes]
+        pub struct Probes;
+
+        #[block("id")]
+        pub fn id(a: u8) -> (u8, Marker) {
+            (a, Marker)
+        }
+    }
+
+    #[test]
+    fn other_unit_structs() {
+        let ((a, marker), probes) = Probes::record(|| id(3));
+        assert_eq!((a, marker), (3, Marker));
+
+        let Probes { id_a, id_out } = probes;
+        if cfg!(feature = "probe") {
+            assert_eq!(id_a, Some(3));
+            assert_eq!(id_out, Some((3, Marker)));
+        } else {
+            assert_eq!((id_a, id_out), (None, None));
+        }
+    }
+}
@@ -1,2 +1,344 @@
-use std::ops::Add;
+//! Values once they've left the type system.
+//!
+//! Op outputs can be all sorts of types (`u16`, `bool`, `Wire`s of various
+//! widths, ...). That's great while an expression is being built and executed
+//! but things that walk over evaluated expressions (i.e. [`OpGraph`]s) need a
+//! single type they can hold onto; that's what [`Bits`] is.
+//!
+//! [`OpGraph`]: ../ops/struct.OpGraph.html
+//! [`Bits`]: struct.Bits.html
 
+use crate::wires::{BitCountType, FitsInU128, Wire};
+
+use core::fmt::{self, Binary, Display, LowerHex, UpperHex};
+use core::ops::{Add, BitAnd, BitOr, BitXor, Not, Sub};
+
+/// A value with a width (in bits) that's only known at runtime.
+///
+/// Values are stored in a `u128` so `Bits` can't be wider than 128 bits (which
+/// is _plenty_ for anything we'd want to graph).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Bits {
+    width: BitCountType,
+    val: u128,
+}
+
+impl Bits {
+    /// The widest `Bits` we can represent.
+    pub const MAX_WIDTH: BitCountType = 128;
+
+    /// Makes a new `Bits` of the given width, dropping any bits of `val` that
+    /// don't fit.
+    pub fn new(width: BitCountType, val: u128) -> Self {
+        assert!(width <= Self::MAX_WIDTH, "`Bits` can be at most {} bits wide; got {}", Self::MAX_WIDTH, width);
+
+        Bits { width, val: val & Self::mask(width) }
+    }
+
+    /// A `u128` with the bottom `width` bits set.
+    pub fn mask(width: BitCountType) -> u128 {
+        if width >= Self::MAX_WIDTH {
+            core::u128::MAX
+        } else {
+            (1u128 << width) - 1
+        }
+    }
+
+    /// The number of bits.
+    pub fn width(&self) -> BitCountType {
+        self.width
+    }
+
+    /// The value, zero extended to 128 bits.
+    pub fn val(&self) -> u128 {
+        self.val
+    }
+
+    /// Shows the value as a sized hex literal, the way wires show themselves
+    /// (`8'h05`).
+    pub fn literal(self) -> Literal {
+        Literal(self)
+    }
+
+    /// The value, sign extended to 128 bits.
+    pub fn signed(&self) -> i128 {
+        if self.width == 0 {
+            0
+        } else {
+            let shift = Self::MAX_WIDTH - self.width;
+            ((self.val << shift) as i128) >> shift
+        }
+    }
+
+    /// Returns bit `idx` (0 is the least significant bit).
+    pub fn bit(&self, idx: BitCountType) -> bool {
+        idx < self.width && (self.val >> idx) & 1 == 1
+    }
+
+    /// Whether every bit is set.
+    pub fn is_ones(&self) -> bool {
+        self.val == Self::mask(self.width)
+    }
+
+    /// Zero extends (or truncates) to `width` bits.
+    pub fn zext(self, width: BitCountType) -> Self {
+        Bits::new(width, self.val)
+    }
+
+    /// Sign extends (or truncates) to `width` bits.
+    pub fn sext(self, width: BitCountType) -> Self {
+        Bits::new(width, self.signed() as u128)
+    }
+}
+
+// Like `Wire`s, `Bits` are fixed width and wrap on overflow. Both sides should
+// be the same width; the result has the width of the left hand side.
+macro_rules! bits_op {
+    ($trait:ident, $fn:ident, |$l:ident, $r:ident| $expr:expr) => {
+        impl $trait for Bits {
+            type Output = Bits;
+
+            fn $fn(self, rhs: Bits) -> Bits {
+                debug_assert_eq!(self.width, rhs.width);
+                let ($l, $r) = (self.val, rhs.val);
+
+                Bits::new(self.width, $expr)
+            }
+        }
+    };
+}
+
+bits_op!(Add, add, |l, r| l.wrapping_add(r));
+bits_op!(Sub, sub, |l, r| l.wrapping_sub(r));
+bits_op!(BitAnd, bitand, |l, r| l & r);
+bits_op!(BitOr, bitor, |l, r| l | r);
+bits_op!(BitXor, bitxor, |l, r| l ^ r);
+
+impl Not for Bits {
+    type Output = Bits;
+
+    fn not(self) -> Bits {
+        Bits::new(self.width, !self.val)
+    }
+}
+
+impl Display for Bits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.val, f)
+    }
+}
+
+/// A [`Bits`](struct.Bits.html) shown as a sized hex literal (`8'h05`), with
+/// a digit for every 4 bits; see [`Bits::literal`](struct.Bits.html#method.literal).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Literal(Bits);
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Literal(b) = self;
+        let digits = ((b.width + 3) / 4).max(1);
+
+        write!(f, "{}'h{:0digits$x}", b.width, b.val, digits = digits)
+    }
+}
+
+impl LowerHex for Bits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        LowerHex::fmt(&self.val, f)
+    }
+}
+
+impl UpperHex for Bits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        UpperHex::fmt(&self.val, f)
+    }
+}
+
+impl Binary for Bits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Binary::fmt(&self.val, f)
+    }
+}
+
+/// Things that can be the output of an op.
+///
+/// Values need to know how wide they are and how to turn themselves into
+/// [`Bits`](struct.Bits.html) so that they can be recorded in an
+/// [`OpGraph`](../ops/struct.OpGraph.html). Note that this doesn't require
+/// `Display`; only ops that print their values (i.e. `Imm`) ask for that.
+pub trait Value {
+    /// The number of bits in this value.
+    fn width(&self) -> BitCountType;
+
+    /// This value as `Bits`.
+    fn to_bits(&self) -> Bits;
+}
+
+/// Values that can be made out of [`Bits`](struct.Bits.html).
+///
+/// Unlike `Value`, this is only for types where every value has the same
+/// width. Ops that produce values of a caller-chosen type (slices, concats, ...)
+/// use this.
+pub trait FromBits: Value + Sized {
+    /// The width of every value of this type.
+    const WIDTH: BitCountType;
+
+    /// Makes a value out of the bottom `WIDTH` bits of `bits`.
+    fn from_bits(bits: Bits) -> Self;
+}
+
+macro_rules! value_impl {
+    ($type:ty) => {
+        impl Value for $type {
+            fn width(&self) -> BitCountType {
+                8 * core::mem::size_of::<$type>()
+            }
+
+            fn to_bits(&self) -> Bits {
+                Bits::new(self.width(), *self as u128)
+            }
+        }
+
+        impl FromBits for $type {
+            const WIDTH: BitCountType = 8 * core::mem::size_of::<$type>();
+
+            fn from_bits(bits: Bits) -> Self {
+                bits.val() as $type
+            }
+        }
+    };
+}
+
+value_impl!(u8);
+value_impl!(u16);
+value_impl!(u32);
+value_impl!(u64);
+value_impl!(usize);
+
+impl Value for u128 {
+    fn width(&self) -> BitCountType {
+        128
+    }
+
+    fn to_bits(&self) -> Bits {
+        Bits::new(128, *self)
+    }
+}
+
+impl FromBits for u128 {
+    const WIDTH: BitCountType = 128;
+
+    fn from_bits(bits: Bits) -> Self {
+        bits.val()
+    }
+}
+
+impl Value for bool {
+    fn width(&self) -> BitCountType {
+        1
+    }
+
+    fn to_bits(&self) -> Bits {
+        Bits::new(1, *self as u128)
+    }
+}
+
+impl FromBits for bool {
+    const WIDTH: BitCountType = 1;
+
+    fn from_bits(bits: Bits) -> Self {
+        bits.val() & 1 == 1
+    }
+}
+
+impl<const B: BitCountType, const S: usize> Value for Wire<{ B }, { S }>
+where
+    Wire<{ B }, { S }>: FitsInU128,
+{
+    fn width(&self) -> BitCountType {
+        B
+    }
+
+    fn to_bits(&self) -> Bits {
+        Bits::new(B, u128::from(*self))
+    }
+}
+
+impl<const B: BitCountType, const S: usize> FromBits for Wire<{ B }, { S }>
+where
+    Wire<{ B }, { S }>: FitsInU128,
+{
+    const WIDTH: BitCountType = B;
+
+    fn from_bits(bits: Bits) -> Self {
+        let mut wire = Self::new();
+        let _ = wire.set(bits.val() & Bits::mask(B));
+
+        wire
+    }
+}
+
+impl Value for Bits {
+    fn width(&self) -> BitCountType {
+        self.width
+    }
+
+    fn to_bits(&self) -> Bits {
+        *self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncates() {
+        assert_eq!(Bits::new(4, 0xFF).val(), 0xF);
+        assert_eq!(Bits::new(0, 0xFF).val(), 0);
+        assert_eq!(Bits::new(128, core::u128::MAX).val(), core::u128::MAX);
+    }
+
+    #[test]
+    fn signed() {
+        assert_eq!(Bits::new(4, 0b1000).signed(), -8);
+        assert_eq!(Bits::new(4, 0b0111).signed(), 7);
+        assert_eq!(Bits::new(16, 0xFFFF).signed(), -1);
+        assert_eq!(Bits::new(0, 0).signed(), 0);
+    }
+
+    #[test]
+    fn arith_wraps() {
+        assert_eq!(Bits::new(8, 250) + Bits::new(8, 10), Bits::new(8, 4));
+        assert_eq!(Bits::new(8, 0) - Bits::new(8, 1), Bits::new(8, 255));
+        assert_eq!(!Bits::new(4, 0b0101), Bits::new(4, 0b1010));
+        assert!(Bits::new(12, 0xFFF).is_ones());
+    }
+
+    #[test]
+    fn extend() {
+        assert_eq!(Bits::new(4, 0b1000).zext(8), Bits::new(8, 0b0000_1000));
+        assert_eq!(Bits::new(4, 0b1000).sext(8), Bits::new(8, 0b1111_1000));
+        assert_eq!(Bits::new(4, 0b0100).sext(8), Bits::new(8, 0b0000_0100));
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(200u8.to_bits(), Bits::new(8, 200));
+        assert_eq!(true.to_bits(), Bits::new(1, 1));
+        assert_eq!(0xBEEFu16.to_bits().width(), 16);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn literals() {
+        use crate::w;
+
+        assert_eq!(format!("{}", Bits::new(8, 5).literal()), "8'h05");
+        assert_eq!(format!("{}", Bits::new(0, 0).literal()), "0'h0");
+
+        // Same as wires:
+        assert_eq!(format!("{}", w!(5#0x16).to_bits().literal()), format!("{}", w!(5#0x16)));
+        assert_eq!(format!("{}", w!(1#1).to_bits().literal()), format!("{}", w!(1#1)));
+    }
+}
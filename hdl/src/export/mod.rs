@@ -0,0 +1,469 @@
+//! Turning designs into something other tools understand.
+//!
+//! Exporters work from a [`Design`]: a description of a design's ports and
+//! state elements where all of the combinational logic (the value of each
+//! output, the next value of each register, ...) is given as an
+//! [`OpGraph`]. A [`Simulator`] can describe the design it owns (see
+//! [`Simulator::design`]) or one can be put together by hand.
+//!
+//! [`Design`]: struct.Design.html
+//! [`OpGraph`]: ../ops/struct.OpGraph.html
+//! [`Simulator`]: ../seq/struct.Simulator.html
+//! [`Simulator::design`]: ../seq/struct.Simulator.html#method.design
+
+mod verilog;
+mod vhdl;
+
+use crate::bits::Bits;
+use crate::ops::{identical, Compared, NodeId, OpData, OpGraph, OpKind};
+use crate::seq::Reset;
+use crate::wires::BitCountType;
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+
+/// An input port.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortDesc {
+    /// The port's name.
+    pub name: &'static str,
+    /// How many bits wide the port is.
+    pub width: BitCountType,
+}
+
+/// A register: its value comes from its own output on the clock edge.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegisterDesc {
+    /// The register's name.
+    pub name: &'static str,
+    /// The register's reset (and initial) value; this also gives its width.
+    pub init: Bits,
+    /// The next-value input. Registers without one hold their value.
+    pub next: Option<OpGraph>,
+    /// The write enable, if there is one.
+    pub enable: Option<OpGraph>,
+    /// The reset signal and when it takes effect, if there is one.
+    pub reset: Option<(OpGraph, Reset)>,
+}
+
+/// A synchronous write port of a memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WritePortDesc {
+    /// Where to write.
+    pub addr: OpGraph,
+    /// What to write.
+    pub data: OpGraph,
+    /// Whether to write (on a given clock edge).
+    pub enable: OpGraph,
+}
+
+/// A memory block; a ROM if it doesn't have any write ports.
+///
+/// Reads show up in graphs as `MemoryRead` nodes and registered reads show up
+/// as registers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryDesc {
+    /// The memory's name.
+    pub name: &'static str,
+    /// How many bits the memory's addresses have.
+    pub addr_width: BitCountType,
+    /// How many bits each location holds.
+    pub data_width: BitCountType,
+    /// The memory's contents (starting at address 0).
+    pub contents: Vec<Bits>,
+    /// The memory's write ports, in order (when ports collide, later ports
+    /// win).
+    pub writes: Vec<WritePortDesc>,
+}
+
+/// Everything an exporter needs to know about a design.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Design {
+    /// The design's input ports. Exporters also pick up any `Input` nodes in
+    /// the design's graphs that aren't listed here.
+    pub inputs: Vec<PortDesc>,
+    /// The design's outputs: names and the logic that produces them.
+    pub outputs: Vec<(&'static str, OpGraph)>,
+    /// The design's registers.
+    pub registers: Vec<RegisterDesc>,
+    /// The design's memories.
+    pub memories: Vec<MemoryDesc>,
+}
+
+impl Design {
+    /// An empty design.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every graph in the design along with a name for it (that's unique
+    /// within the design), in the order exporters should emit them.
+    pub fn graphs(&self) -> Vec<(String, &OpGraph)> {
+        let mut graphs: Vec<(String, &OpGraph)> = Vec::new();
+
+        for (name, g) in self.outputs.iter() {
+            graphs.push((String::from(*name), g));
+        }
+
+        for r in self.registers.iter() {
+            if let Some(g) = &r.next {
+                graphs.push((format!("{}_next", r.name), g));
+            }
+            if let Some(g) = &r.enable {
+                graphs.push((format!("{}_en", r.name), g));
+            }
+            if let Some((g, _)) = &r.reset {
+                graphs.push((format!("{}_rst", r.name), g));
+            }
+        }
+
+        for m in self.memories.iter() {
+            for (i, w) in m.writes.iter().enumerate() {
+                graphs.push((format!("{}_w{}_addr", m.name, i), &w.addr));
+                graphs.push((format!("{}_w{}_data", m.name, i), &w.data));
+                graphs.push((format!("{}_w{}_en", m.name, i), &w.enable));
+            }
+        }
+
+        graphs
+    }
+
+    /// The design's input ports: the ones in [`inputs`](#structfield.inputs)
+    /// followed by any others that the design's graphs read from.
+    pub fn all_inputs(&self) -> Vec<PortDesc> {
+        let mut ports = self.inputs.clone();
+
+        for (_, g) in self.graphs() {
+            for node in g.nodes() {
+                if let OpData::Input { name } = *node.data() {
+                    if !ports.iter().any(|p| p.name == name) {
+                        ports.push(PortDesc { name, width: node.value().width() });
+                    }
+                }
+            }
+        }
+
+        ports
+    }
+}
+
+/// Why a design couldn't be exported.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExportError {
+    /// The design uses a kind of op that has no hardware equivalent; i.e.
+    /// reads of the register file or memory in the context (`RegisterInput`
+    /// and `MemoryInput`). Use ports, registers, and memory blocks instead.
+    Unsupported(OpKind),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Unsupported(kind) => write!(f, "`{:?}` ops can't be exported", kind),
+        }
+    }
+}
+
+/// Turns a name into something that's a valid identifier in most HDLs: ASCII
+/// letters, digits, and underscores, not starting with a digit.
+///
+/// This doesn't know about any particular HDL's reserved words; exporters get
+/// their identifiers from [`Names`](struct.Names.html).
+pub(crate) fn ident(name: &str) -> String {
+    let mut s: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if !s.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        s.insert(0, 'x');
+    }
+
+    s
+}
+
+/// The rules for identifiers in an HDL.
+pub(crate) struct Syntax {
+    /// Words that can't be identifiers (in lower case). Exporters also put the
+    /// names of things they refer to (types, functions, ...) here so that
+    /// signals don't shadow them.
+    pub reserved: &'static [&'static str],
+    /// Whether identifiers that only differ in case are the same identifier.
+    pub case_insensitive: bool,
+    /// Whether identifiers can have consecutive underscores or end with one.
+    pub loose_underscores: bool,
+}
+
+/// The things in a design that have names. Names only have to be unique
+/// within a kind; an input and a register can both be called `a`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Role {
+    Input,
+    Output,
+    Register,
+    Memory,
+}
+
+/// The identifiers used in an exported design.
+///
+/// Every identifier is handed out once, so signals can't collide with ports,
+/// registers, or each other, and none of them are reserved words.
+pub(crate) struct Names {
+    syntax: &'static Syntax,
+    taken: BTreeSet<String>,
+    named: BTreeMap<(Role, &'static str), String>,
+}
+
+impl Names {
+    pub fn new(syntax: &'static Syntax) -> Self {
+        Self { syntax, taken: BTreeSet::new(), named: BTreeMap::new() }
+    }
+
+    /// An identifier based on `name` that isn't reserved and hasn't been
+    /// handed out yet: `name` itself if possible, otherwise `name_1`, ...
+    pub fn claim(&mut self, name: &str) -> String {
+        let mut base = ident(name);
+        if !self.syntax.loose_underscores {
+            while let Some(i) = base.find("__") {
+                let _ = base.remove(i);
+            }
+            while base.ends_with('_') {
+                let _ = base.pop();
+            }
+        }
+
+        let mut id = base.clone();
+        for n in 1.. {
+            let mut key = id.clone();
+            if self.syntax.case_insensitive {
+                key.make_ascii_lowercase();
+            }
+
+            if !self.syntax.reserved.contains(&key.as_str()) && self.taken.insert(key) {
+                break;
+            }
+
+            id = format!("{}_{}", base, n);
+        }
+
+        id
+    }
+
+    /// The identifier for the `role` called `name`, claiming one for it if it
+    /// doesn't have one yet.
+    pub fn of(&mut self, role: Role, name: &'static str) -> String {
+        if let Some(id) = self.named.get(&(role, name)) {
+            return id.clone();
+        }
+
+        let id = self.claim(name);
+        let _ = self.named.insert((role, name), id.clone());
+        id
+    }
+}
+
+/// A graph, broken up into the signals an exporter has to declare.
+pub(crate) struct Lowered {
+    /// The nodes that need their own signal, with the signal's name, operands
+    /// before users.
+    pub wires: Vec<(NodeId, String)>,
+    /// How to refer to each node: a signal, port, or register name, or a
+    /// literal. `None` for nodes that aren't needed.
+    pub names: Vec<Option<String>>,
+    /// How to refer to the graph's output.
+    pub root: String,
+}
+
+/// A labeled node that has a signal.
+struct Signal {
+    label: &'static str,
+    /// The graph the node is in (an index into `Netlist::graphs`).
+    graph: usize,
+    node: NodeId,
+    name: String,
+}
+
+/// Every graph in a design, [lowered](#method.lower).
+pub(crate) struct Netlist<'d> {
+    pub graphs: Vec<(&'d OpGraph, Lowered)>,
+    pub names: Names,
+    signals: Vec<Signal>,
+}
+
+impl<'d> Netlist<'d> {
+    /// Lowers `design`'s graphs, naming things with `names`.
+    ///
+    /// Exporters claim the identifiers they use themselves (the module's name,
+    /// `clk`, ...) before this; ports, registers, and memories get theirs next,
+    /// in that order, and then the signals.
+    pub fn new(
+        design: &'d Design,
+        mut names: Names,
+        literal: impl Fn(Bits) -> String,
+    ) -> Result<Self, ExportError> {
+        for p in design.all_inputs() {
+            let _ = names.of(Role::Input, p.name);
+        }
+        for (name, _) in design.outputs.iter() {
+            let _ = names.of(Role::Output, name);
+        }
+        for r in design.registers.iter() {
+            let _ = names.of(Role::Register, r.name);
+        }
+        for m in design.memories.iter() {
+            let _ = names.of(Role::Memory, m.name);
+        }
+
+        let mut net = Self { graphs: Vec::new(), names, signals: Vec::new() };
+        for (name, g) in design.graphs() {
+            let l = net.lower(g, &name, &literal)?;
+            net.graphs.push((g, l));
+        }
+
+        Ok(net)
+    }
+
+    /// The identifier of the `role` called `name` (which must be in the
+    /// design).
+    pub fn name(&self, role: Role, name: &'static str) -> &str {
+        self.names.named.get(&(role, name)).expect("everything in the design to have a name")
+    }
+
+    /// How to refer to the output of `g` (which must be one of the design's
+    /// graphs).
+    pub fn root(&self, g: &OpGraph) -> &str {
+        let (_, l) = self.graphs.iter().find(|(l, _)| core::ptr::eq(*l, g)).unwrap();
+        &l.root
+    }
+
+    /// The signal that node `id` of `g` (the graph being lowered) can use, if
+    /// another node with the same label that computes the same thing already
+    /// has one.
+    fn signal(
+        &self,
+        g: &OpGraph,
+        id: NodeId,
+        memos: &mut BTreeMap<usize, Compared>,
+    ) -> Option<String> {
+        let label = g[id].label()?;
+
+        self.signals
+            .iter()
+            .filter(|s| s.label == label)
+            .find(|s| {
+                let other = self.graphs.get(s.graph).map_or(g, |(o, _)| *o);
+                identical(g, id, other, s.node, memos.entry(s.graph).or_default())
+            })
+            .map(|s| s.name.clone())
+    }
+
+    /// Works out which nodes of `g` need signals and what to call everything.
+    ///
+    /// Constants are folded (into `literal`s) and inputs, registers, and
+    /// memory reads are referred to by name. Labeled nodes get a signal named
+    /// after their label, unless a node with the same label that computes the
+    /// same thing already has one; a node with a label that's already used for
+    /// something else gets a fresh name (`label_1`, ...). Every other node is
+    /// named `<prefix>_n<index>`.
+    fn lower(
+        &mut self,
+        g: &OpGraph,
+        prefix: &str,
+        literal: impl Fn(Bits) -> String,
+    ) -> Result<Lowered, ExportError> {
+        use OpKind::*;
+
+        let root = match g.root() {
+            Some(root) => root,
+            None => {
+                return Ok(Lowered { wires: Vec::new(), names: Vec::new(), root: String::new() })
+            }
+        };
+
+        let is_input = |kind| match kind {
+            RegisterInput | MemoryInput | Register | Input | MemoryRead => true,
+            _ => false,
+        };
+
+        let mut constant: Vec<bool> = Vec::with_capacity(g.len());
+        for node in g.nodes() {
+            let ops = node.operands();
+            let c = node.kind() == Immediate
+                || (!is_input(node.kind())
+                    && !ops.is_empty()
+                    && ops.iter().all(|o| constant[o.idx()]));
+
+            constant.push(c);
+        }
+
+        // Nodes that are already signals in other graphs don't need their
+        // operands.
+        let mut memos = BTreeMap::new();
+        let existing: Vec<Option<String>> = g
+            .ids()
+            .map(|id| {
+                if is_input(g[id].kind()) || constant[id.idx()] {
+                    None
+                } else {
+                    self.signal(g, id, &mut memos)
+                }
+            })
+            .collect();
+
+        let mut needed = vec![false; g.len()];
+        needed[root.idx()] = true;
+        for id in g.ids().rev() {
+            if needed[id.idx()] && !constant[id.idx()] && existing[id.idx()].is_none() {
+                for o in g[id].operands() {
+                    needed[o.idx()] = true;
+                }
+            }
+        }
+
+        let mut wires = Vec::new();
+        let mut names = Vec::with_capacity(g.len());
+        for id in g.ids() {
+            let node = &g[id];
+
+            let name = if !needed[id.idx()] {
+                None
+            } else if constant[id.idx()] {
+                Some(literal(node.value()))
+            } else {
+                Some(match *node.data() {
+                    OpData::RegisterInput { .. } | OpData::MemoryInput { .. } => {
+                        return Err(ExportError::Unsupported(node.kind()))
+                    }
+                    OpData::Register { name } => self.names.of(Role::Register, name),
+                    OpData::Input { name } => self.names.of(Role::Input, name),
+                    _ => match (node.label(), self.signal(g, id, &mut memos)) {
+                        (_, Some(name)) => name,
+                        (Some(label), None) => {
+                            let name = self.names.claim(label);
+                            self.signals.push(Signal {
+                                label,
+                                graph: self.graphs.len(),
+                                node: id,
+                                name: name.clone(),
+                            });
+                            wires.push((id, name.clone()));
+                            name
+                        }
+                        (None, None) => {
+                            let name = self.names.claim(&format!("{}_n{}", prefix, id.idx()));
+                            wires.push((id, name.clone()));
+                            name
+                        }
+                    },
+                })
+            };
+
+            names.push(name);
+        }
+
+        let root = names[root.idx()].clone().unwrap();
+        Ok(Lowered { wires, names, root })
+    }
+}
@@ -0,0 +1,356 @@
+//! Verilog-2005 output.
+
+use super::{Design, ExportError, Names, Netlist, Role, Syntax};
+use crate::bits::Bits;
+use crate::ops::{OpGraph, OpGraphNode, OpKind, Param};
+use crate::seq::Reset;
+use crate::wires::BitCountType;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Verilog-2005's keywords.
+static SYNTAX: Syntax = Syntax {
+    reserved: &[
+        "always", "and", "assign", "automatic", "begin", "buf", "bufif0", "bufif1", "case",
+        "casex", "casez", "cell", "cmos", "config", "deassign", "default", "defparam", "design",
+        "disable", "edge", "else", "end", "endcase", "endconfig", "endfunction", "endgenerate",
+        "endmodule", "endprimitive", "endspecify", "endtable", "endtask", "event", "for", "force",
+        "forever", "fork", "function", "generate", "genvar", "highz0", "highz1", "if", "ifnone",
+        "incdir", "include", "initial", "inout", "input", "instance", "integer", "join", "large",
+        "liblist", "library", "localparam", "macromodule", "medium", "module", "nand", "negedge",
+        "nmos", "nor", "noshowcancelled", "not", "notif0", "notif1", "or", "output", "parameter",
+        "pmos", "posedge", "primitive", "pull0", "pull1", "pulldown", "pullup",
+        "pulsestyle_ondetect", "pulsestyle_onevent", "rcmos", "real", "realtime", "reg",
+        "release", "repeat", "rnmos", "rpmos", "rtran", "rtranif0", "rtranif1", "scalared",
+        "showcancelled", "signed", "small", "specify", "specparam", "strong0", "strong1",
+        "supply0", "supply1", "table", "task", "time", "tran", "tranif0", "tranif1", "tri",
+        "tri0", "tri1", "triand", "trior", "trireg", "unsigned", "use", "uwire", "vectored",
+        "wait", "wand", "weak0", "weak1", "while", "wire", "wor", "xnor", "xor",
+    ],
+    case_insensitive: false,
+    loose_underscores: true,
+};
+
+fn literal(b: Bits) -> String {
+    format!("{}", b.literal())
+}
+
+fn range(width: BitCountType) -> String {
+    format!("[{}:0]", width - 1)
+}
+
+/// The right hand side of the assignment for `node`, given the names of its
+/// operands.
+fn expr(net: &Netlist<'_>, g: &OpGraph, node: &OpGraphNode, names: &[Option<String>]) -> String {
+    use OpKind::*;
+
+    let ids = node.operands();
+    let o: Vec<&str> = ids.iter().map(|id| names[id.idx()].as_ref().unwrap().as_str()).collect();
+    let w: Vec<BitCountType> = ids.iter().map(|id| g[*id].value().width()).collect();
+    let width = node.value().width();
+
+    match node.kind() {
+        Addition => format!("{} + {}", o[0], o[1]),
+        Subtraction => format!("{} - {}", o[0], o[1]),
+        And => format!("{} & {}", o[0], o[1]),
+        Or => format!("{} | {}", o[0], o[1]),
+        Xor => format!("{} ^ {}", o[0], o[1]),
+        Not => format!("~{}", o[0]),
+        Mux => format!("{} ? {} : {}", o[0], o[1], o[2]),
+        MuxN => {
+            let mut s = String::new();
+            for (i, input) in o[1..o.len() - 1].iter().enumerate() {
+                s.push_str(&format!("{} == {}'d{} ? {} : ", o[0], w[0], i, input));
+            }
+            s.push_str(o[o.len() - 1]);
+            s
+        }
+        Zext if width == w[0] => String::from(o[0]),
+        Zext => format!("{{{}'h0, {}}}", width - w[0], o[0]),
+        Sext if width == w[0] => String::from(o[0]),
+        Sext => format!("{{{{{}{{{}[{}]}}}}, {}}}", width - w[0], o[0], w[0] - 1, o[0]),
+        Eq => format!("{} == {}", o[0], o[1]),
+        Ne => format!("{} != {}", o[0], o[1]),
+        Ult => format!("{} < {}", o[0], o[1]),
+        Ule => format!("{} <= {}", o[0], o[1]),
+        Slt => format!("$signed({}) < $signed({})", o[0], o[1]),
+        Sle => format!("$signed({}) <= $signed({})", o[0], o[1]),
+        Slice => match node.data().param() {
+            Param::Range { hi, lo } => format!("{}[{}:{}]", o[0], hi, lo),
+            p => unreachable!("slices to have a range; got {:?}", p),
+        },
+        Concat => format!("{{{}, {}}}", o[0], o[1]),
+        MemoryRead => match node.data().param() {
+            Param::Name(name) => format!("{}[{}]", net.name(Role::Memory, name), o[0]),
+            p => unreachable!("memory reads to have a name; got {:?}", p),
+        },
+        kind => unreachable!("`{:?}` nodes to be named, not assigned", kind),
+    }
+}
+
+impl Design {
+    /// Renders the design as a synthesizable Verilog-2005 module named
+    /// `module`.
+    ///
+    /// Inputs become input ports and outputs become output ports. Labeled ops
+    /// become `wire`s named after their labels; other ops get wires named after
+    /// the output (or register, ...) they feed. If the design has any
+    /// registers or memories the module also gets a `clk` input and they're
+    /// updated in `always @(posedge clk)` blocks.
+    ///
+    /// Names that aren't valid Verilog identifiers (or that are keywords, or
+    /// that something else in the module already uses) are changed to ones
+    /// that are: an input called `reg` becomes `reg_1`.
+    pub fn to_verilog(&self, module: &str) -> Result<String, ExportError> {
+        let mut names = Names::new(&SYNTAX);
+        let module = names.claim(module);
+        let clk = names.claim("clk");
+
+        let net = Netlist::new(self, names, literal)?;
+        let root = |g: &OpGraph| net.root(g);
+
+        let mut ports = Vec::new();
+        if !self.registers.is_empty() || !self.memories.is_empty() {
+            ports.push(format!("    input wire {}", clk));
+        }
+        for p in self.all_inputs() {
+            let name = net.name(Role::Input, p.name);
+            ports.push(format!("    input wire {} {}", range(p.width), name));
+        }
+        for (name, g) in self.outputs.iter() {
+            let width = range(g.root().map_or(1, |r| g[r].value().width()));
+            ports.push(format!("    output wire {} {}", width, net.name(Role::Output, name)));
+        }
+
+        let mut v = format!("module {} (\n{}\n);\n", module, ports.join(",\n"));
+
+        for r in self.registers.iter() {
+            let (name, width) = (net.name(Role::Register, r.name), range(r.init.width()));
+            v.push_str(&format!("    reg {} {} = {};\n", width, name, literal(r.init)));
+        }
+        for m in self.memories.iter() {
+            let name = net.name(Role::Memory, m.name);
+            let depth = m.contents.len() - 1;
+            v.push_str(&format!("    reg {} {} [0:{}];\n", range(m.data_width), name, depth));
+
+            let init: Vec<(usize, &Bits)> =
+                m.contents.iter().enumerate().filter(|(_, b)| b.val() != 0).collect();
+            if !init.is_empty() {
+                v.push_str("    initial begin\n");
+                for (addr, word) in init {
+                    v.push_str(&format!("        {}[{}] = {};\n", name, addr, literal(*word)));
+                }
+                v.push_str("    end\n");
+            }
+        }
+
+        v.push('\n');
+        for (g, l) in net.graphs.iter() {
+            for (id, name) in l.wires.iter() {
+                let node = &g[*id];
+                let (width, rhs) = (range(node.value().width()), expr(&net, g, node, &l.names));
+                v.push_str(&format!("    wire {} {} = {};\n", width, name, rhs));
+            }
+        }
+        for (name, g) in self.outputs.iter() {
+            v.push_str(&format!("    assign {} = {};\n", net.name(Role::Output, name), root(g)));
+        }
+
+        for r in self.registers.iter() {
+            let name = net.name(Role::Register, r.name);
+            let next = r.next.as_ref().map(|g| root(g));
+            let enable = r.enable.as_ref().map(|g| root(g));
+            let reset = r.reset.as_ref().map(|(g, kind)| (root(g), *kind));
+
+            let mut arms: Vec<(Option<&str>, String)> = Vec::new();
+            if let Some((rst, _)) = reset {
+                arms.push((Some(rst), literal(r.init)));
+            }
+            if let Some(next) = next {
+                arms.push((enable, String::from(next)));
+            }
+            if arms.is_empty() {
+                continue;
+            }
+
+            let sensitivity = match reset {
+                Some((rst, Reset::Async)) => format!("posedge {} or posedge {}", clk, rst),
+                _ => format!("posedge {}", clk),
+            };
+
+            v.push_str(&format!("\n    always @({}) begin\n", sensitivity));
+            for (i, (cond, val)) in arms.iter().enumerate() {
+                let kw = match (i, cond) {
+                    (0, Some(c)) => format!("if ({}) ", c),
+                    (_, Some(c)) => format!("else if ({}) ", c),
+                    (0, None) => String::new(),
+                    (_, None) => String::from("else "),
+                };
+                v.push_str(&format!("        {}{} <= {};\n", kw, name, val));
+            }
+            v.push_str("    end\n");
+        }
+
+        for m in self.memories.iter().filter(|m| !m.writes.is_empty()) {
+            v.push_str(&format!("\n    always @(posedge {}) begin\n", clk));
+            for w in m.writes.iter() {
+                v.push_str(&format!(
+                    "        if ({}) {}[{}] <= {};\n",
+                    root(&w.enable), net.name(Role::Memory, m.name), root(&w.addr), root(&w.data)
+                ));
+            }
+            v.push_str("    end\n");
+        }
+
+        v.push_str("endmodule\n");
+        Ok(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::{Imm, OpWrapper, RegisterInput};
+    use crate::seq::{Input, Ram, Register, Simulator};
+    use crate::state::Registers;
+    use crate::w;
+    use crate::wires::Wire;
+
+    type W8 = Wire<{ 8 }, { 1 }>;
+
+    #[test]
+    fn counter() {
+        let en: Input<{ 1 }> = Input::new("en", w!(1#1));
+        let rst: Input<{ 1 }> = Input::new("rst", w!(1#0));
+        let cnt: Register<(), { 8 }> = Register::new("cnt", w!(8#0));
+
+        let one: OpWrapper<Imm<W8>> = w!(8#1).into();
+        let ff: OpWrapper<Imm<W8>> = w!(8#0xFF).into();
+        let _ = cnt
+            .next((cnt.out() + one).label("inc"))
+            .enable_when(en.out())
+            .reset_when(rst.out(), Reset::Sync);
+
+        let mut sim = Simulator::new(());
+        let _ = sim.add_clocked(cnt.clone()).add_input(en).add_input(rst).probe("full", cnt.out().eq(ff));
+
+        assert_eq!(
+            sim.design().to_verilog("counter").unwrap(),
+            "module counter (\n\
+            \x20   input wire clk,\n\
+            \x20   input wire [0:0] en,\n\
+            \x20   input wire [0:0] rst,\n\
+            \x20   output wire [0:0] full\n\
+            );\n\
+            \x20   reg [7:0] cnt = 8'h00;\n\
+            \n\
+            \x20   wire [0:0] full_n2 = cnt == 8'hff;\n\
+            \x20   wire [7:0] inc = cnt + 8'h01;\n\
+            \x20   assign full = full_n2;\n\
+            \n\
+            \x20   always @(posedge clk) begin\n\
+            \x20       if (rst) cnt <= 8'h00;\n\
+            \x20       else if (en) cnt <= inc;\n\
+            \x20   end\n\
+            endmodule\n"
+        );
+    }
+
+    #[test]
+    fn memory() {
+        let addr: Input<{ 2 }> = Input::new("addr", w!(2#0));
+        let data: Input<{ 8 }> = Input::new("data", w!(8#0));
+        let we: Input<{ 1 }> = Input::new("we", w!(1#0));
+        let ram: Ram<(), { 2 }, { 8 }> = Ram::new("ram");
+        let _ = ram.write_port(addr.out(), data.out(), we.out());
+
+        let mut sim = Simulator::new(());
+        let _ = sim
+            .add_clocked(ram.clone())
+            .add_input(addr.clone())
+            .add_input(data)
+            .add_input(we)
+            .probe("q", ram.read(addr.out()));
+
+        assert_eq!(
+            sim.design().to_verilog("mem").unwrap(),
+            "module mem (\n\
+            \x20   input wire clk,\n\
+            \x20   input wire [1:0] addr,\n\
+            \x20   input wire [7:0] data,\n\
+            \x20   input wire [0:0] we,\n\
+            \x20   output wire [7:0] q\n\
+            );\n\
+            \x20   reg [7:0] ram [0:3];\n\
+            \n\
+            \x20   wire [7:0] q_n1 = ram[addr];\n\
+            \x20   assign q = q_n1;\n\
+            \n\
+            \x20   always @(posedge clk) begin\n\
+            \x20       if (we) ram[addr] <= data;\n\
+            \x20   end\n\
+            endmodule\n"
+        );
+    }
+
+    #[test]
+    fn names() {
+        let a: Input<{ 8 }> = Input::new("reg", w!(8#0));
+        let b: Input<{ 8 }> = Input::new("clk", w!(8#0));
+        let t: Register<(), { 8 }> = Register::new("t", w!(8#0));
+
+        // Labels are only shared by ops that compute the same thing.
+        let _ = t.next((a.out() + b.out()).label("t"));
+
+        let mut sim = Simulator::new(());
+        let _ = sim
+            .add_clocked(t.clone())
+            .add_input(a.clone())
+            .add_input(b.clone())
+            .probe("sum", (a.out() + b.out()).label("t"))
+            .probe("diff", (a.out() - b.out()).label("t"));
+
+        assert_eq!(
+            sim.design().to_verilog("module").unwrap(),
+            "module module_1 (\n\
+            \x20   input wire clk,\n\
+            \x20   input wire [7:0] reg_1,\n\
+            \x20   input wire [7:0] clk_1,\n\
+            \x20   output wire [7:0] sum,\n\
+            \x20   output wire [7:0] diff\n\
+            );\n\
+            \x20   reg [7:0] t = 8'h00;\n\
+            \n\
+            \x20   wire [7:0] t_1 = reg_1 + clk_1;\n\
+            \x20   wire [7:0] t_2 = reg_1 - clk_1;\n\
+            \x20   assign sum = t_1;\n\
+            \x20   assign diff = t_2;\n\
+            \n\
+            \x20   always @(posedge clk) begin\n\
+            \x20       t <= t_1;\n\
+            \x20   end\n\
+            endmodule\n"
+        );
+    }
+
+    struct Reg;
+
+    impl Registers for Reg {
+        type Word = u8;
+
+        fn read_reg(&self, _num: u8) -> u8 {
+            0
+        }
+
+        fn write_reg(&self, _num: u8, _word: u8) {}
+    }
+
+    #[test]
+    fn context_reads() {
+        let mut sim = Simulator::new(Reg);
+        let _ = sim.probe("r0", RegisterInput::new(0));
+
+        assert_eq!(sim.design().to_verilog("m"), Err(ExportError::Unsupported(OpKind::RegisterInput)));
+    }
+}
@@ -0,0 +1,443 @@
+//! VHDL-93 output.
+
+use super::{Design, ExportError, Names, Netlist, Role, Syntax};
+use crate::bits::Bits;
+use crate::ops::{OpGraph, OpGraphNode, OpKind, Param};
+use crate::seq::Reset;
+use crate::wires::BitCountType;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// VHDL-93's reserved words and the names from `ieee` (and the architecture's
+/// name) that the output uses.
+static SYNTAX: Syntax = Syntax {
+    reserved: &[
+        "abs", "access", "after", "alias", "all", "and", "architecture", "array", "assert",
+        "attribute", "begin", "block", "body", "buffer", "bus", "case", "component",
+        "configuration", "constant", "disconnect", "downto", "else", "elsif", "end", "entity",
+        "exit", "file", "for", "function", "generate", "generic", "group", "guarded", "if",
+        "impure", "in", "inertial", "inout", "is", "label", "library", "linkage", "literal",
+        "loop", "map", "mod", "nand", "new", "next", "nor", "not", "null", "of", "on", "open",
+        "or", "others", "out", "package", "port", "postponed", "procedure", "process", "pure",
+        "range", "record", "register", "reject", "rem", "report", "return", "rol", "ror",
+        "select", "severity", "shared", "signal", "sla", "sll", "sra", "srl", "subtype", "then",
+        "to", "transport", "type", "unaffected", "units", "until", "use", "variable", "wait",
+        "when", "while", "with", "xnor", "xor",
+        "ieee", "numeric_std", "resize", "rising_edge", "rtl", "signed", "std_logic",
+        "std_logic_1164", "std_logic_vector", "to_integer", "unsigned",
+    ],
+    case_insensitive: true,
+    loose_underscores: false,
+};
+
+/// A `std_logic_vector` literal, in binary (hex literals only work for
+/// multiples of 4 bits in VHDL-93).
+fn literal(b: Bits) -> String {
+    let mut s = String::with_capacity(b.width() + 2);
+
+    s.push('"');
+    for i in (0..b.width()).rev() {
+        s.push(if b.bit(i) { '1' } else { '0' });
+    }
+    s.push('"');
+
+    s
+}
+
+fn vector(width: BitCountType) -> String {
+    format!("std_logic_vector({} downto 0)", width - 1)
+}
+
+/// `o` as a `numeric_std` type (`unsigned` or `signed`). Literals have to be
+/// qualified rather than converted.
+fn num(ty: &str, o: &str) -> String {
+    if o.starts_with('"') {
+        format!("{}'({})", ty, o)
+    } else {
+        format!("{}({})", ty, o)
+    }
+}
+
+/// The right hand side of the (concurrent) assignment for `node`, given the
+/// names of its operands.
+fn expr(net: &Netlist<'_>, g: &OpGraph, node: &OpGraphNode, names: &[Option<String>]) -> String {
+    use OpKind::*;
+
+    let ids = node.operands();
+    let o: Vec<&str> = ids.iter().map(|id| names[id.idx()].as_ref().unwrap().as_str()).collect();
+    let w: Vec<BitCountType> = ids.iter().map(|id| g[*id].value().width()).collect();
+    let width = node.value().width();
+
+    let cmp = |ty: &str, op: &str| {
+        format!("\"1\" when {} {} {} else \"0\"", num(ty, o[0]), op, num(ty, o[1]))
+    };
+    let arith = |op: &str| {
+        format!("std_logic_vector({} {} {})", num("unsigned", o[0]), op, num("unsigned", o[1]))
+    };
+
+    match node.kind() {
+        Addition => arith("+"),
+        Subtraction => arith("-"),
+        And => format!("{} and {}", o[0], o[1]),
+        Or => format!("{} or {}", o[0], o[1]),
+        Xor => format!("{} xor {}", o[0], o[1]),
+        Not => format!("not {}", o[0]),
+        Mux => format!("{} when {} = \"1\" else {}", o[1], o[0], o[2]),
+        MuxN => {
+            let mut s = String::new();
+            for (i, input) in o[1..o.len() - 1].iter().enumerate() {
+                let sel = literal(Bits::new(w[0], i as u128));
+                s.push_str(&format!("{} when {} = {} else ", input, o[0], sel));
+            }
+            s.push_str(o[o.len() - 1]);
+            s
+        }
+        Zext => format!("std_logic_vector(resize({}, {}))", num("unsigned", o[0]), width),
+        Sext => format!("std_logic_vector(resize({}, {}))", num("signed", o[0]), width),
+        Eq => format!("\"1\" when {} = {} else \"0\"", o[0], o[1]),
+        Ne => format!("\"1\" when {} /= {} else \"0\"", o[0], o[1]),
+        Ult => cmp("unsigned", "<"),
+        Ule => cmp("unsigned", "<="),
+        Slt => cmp("signed", "<"),
+        Sle => cmp("signed", "<="),
+        Slice => match node.data().param() {
+            Param::Range { hi, lo } => format!("{}({} downto {})", o[0], hi, lo),
+            p => unreachable!("slices to have a range; got {:?}", p),
+        },
+        Concat => format!("{} & {}", o[0], o[1]),
+        MemoryRead => match node.data().param() {
+            Param::Name(name) => {
+                format!("{}(to_integer({}))", net.name(Role::Memory, name), num("unsigned", o[0]))
+            }
+            p => unreachable!("memory reads to have a name; got {:?}", p),
+        },
+        kind => unreachable!("`{:?}` nodes to be named, not assigned", kind),
+    }
+}
+
+/// The statements that load register `name` with its next value (if it has
+/// one), indented by `indent`.
+fn load(name: &str, next: Option<&str>, enable: Option<&str>, indent: &str) -> Option<String> {
+    let next = next?;
+
+    Some(match enable {
+        Some(en) => format!(
+            "{i}if {} = \"1\" then\n{i}    {} <= {};\n{i}end if;\n",
+            en, name, next, i = indent
+        ),
+        None => format!("{}{} <= {};\n", indent, name, next),
+    })
+}
+
+impl Design {
+    /// Renders the design as a VHDL-93 entity named `entity` along with an
+    /// architecture (named `rtl`) for it.
+    ///
+    /// Every port and signal is a `std_logic_vector` (even one bit wide ones)
+    /// and arithmetic goes through `numeric_std`. Otherwise this is laid out
+    /// like [`to_verilog`](#method.to_verilog): labeled ops become signals
+    /// named after their labels and, if there are any registers or memories,
+    /// there's a `clk` port and they're updated in clocked processes.
+    ///
+    /// VHDL identifiers are case insensitive and can't have `__` in them or
+    /// end with `_`, so names are tidied up and made unique with that in mind:
+    /// inputs called `A` and `a` become `A` and `a_1`.
+    pub fn to_vhdl(&self, entity: &str) -> Result<String, ExportError> {
+        let mut names = Names::new(&SYNTAX);
+        let entity = names.claim(entity);
+        let clk = names.claim("clk");
+
+        let mut net = Netlist::new(self, names, literal)?;
+        let mut types = Vec::new();
+        for m in self.memories.iter() {
+            let ty = format!("{}_t", net.name(Role::Memory, m.name));
+            types.push(net.names.claim(&ty));
+        }
+
+        let root = |g: &OpGraph| net.root(g);
+
+        let mut ports = Vec::new();
+        if !self.registers.is_empty() || !self.memories.is_empty() {
+            ports.push(format!("        {} : in std_logic", clk));
+        }
+        for p in self.all_inputs() {
+            let name = net.name(Role::Input, p.name);
+            ports.push(format!("        {} : in {}", name, vector(p.width)));
+        }
+        for (name, g) in self.outputs.iter() {
+            let width = vector(g.root().map_or(1, |r| g[r].value().width()));
+            ports.push(format!("        {} : out {}", net.name(Role::Output, name), width));
+        }
+
+        let mut v = String::from(
+            "library ieee;\nuse ieee.std_logic_1164.all;\nuse ieee.numeric_std.all;\n\n",
+        );
+        v.push_str(&format!(
+            "entity {} is\n    port (\n{}\n    );\nend entity {};\n\n",
+            entity, ports.join(";\n"), entity
+        ));
+        v.push_str(&format!("architecture rtl of {} is\n", entity));
+
+        for r in self.registers.iter() {
+            let (name, ty) = (net.name(Role::Register, r.name), vector(r.init.width()));
+            v.push_str(&format!("    signal {} : {} := {};\n", name, ty, literal(r.init)));
+        }
+        for (m, ty) in self.memories.iter().zip(types.iter()) {
+            let name = net.name(Role::Memory, m.name);
+
+            let mut init: Vec<String> = m
+                .contents
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| b.val() != 0)
+                .map(|(addr, b)| format!("{} => {}", addr, literal(*b)))
+                .collect();
+            init.push(String::from("others => (others => '0')"));
+
+            v.push_str(&format!(
+                "    type {} is array (0 to {}) of {};\n",
+                ty, m.contents.len() - 1, vector(m.data_width)
+            ));
+            v.push_str(&format!("    signal {} : {} := ({});\n", name, ty, init.join(", ")));
+        }
+        for (g, l) in net.graphs.iter() {
+            for (id, name) in l.wires.iter() {
+                v.push_str(&format!("    signal {} : {};\n", name, vector(g[*id].value().width())));
+            }
+        }
+
+        v.push_str("begin\n");
+        for (g, l) in net.graphs.iter() {
+            for (id, name) in l.wires.iter() {
+                v.push_str(&format!("    {} <= {};\n", name, expr(&net, g, &g[*id], &l.names)));
+            }
+        }
+        for (name, g) in self.outputs.iter() {
+            v.push_str(&format!("    {} <= {};\n", net.name(Role::Output, name), root(g)));
+        }
+
+        for r in self.registers.iter() {
+            let name = net.name(Role::Register, r.name);
+            let next = r.next.as_ref().map(|g| root(g));
+            let enable = r.enable.as_ref().map(|g| root(g));
+            let reset = r.reset.as_ref().map(|(g, kind)| (root(g), *kind));
+
+            let load_at = |indent: &str| load(name, next, enable, indent);
+
+            let body = match reset {
+                None => match load_at("            ") {
+                    Some(l) => {
+                        format!("        if rising_edge({}) then\n{}        end if;\n", clk, l)
+                    }
+                    None => continue,
+                },
+                Some((rst, Reset::Sync)) => {
+                    let mut s = format!(
+                        "        if rising_edge({}) then\n            if {} = \"1\" then\n\
+                        \x20               {} <= {};\n",
+                        clk, rst, name, literal(r.init)
+                    );
+                    if let Some(l) = load_at("                ") {
+                        s.push_str(&format!("            else\n{}", l));
+                    }
+                    s.push_str("            end if;\n        end if;\n");
+                    s
+                }
+                Some((rst, Reset::Async)) => {
+                    let mut s = format!(
+                        "        if {} = \"1\" then\n            {} <= {};\n",
+                        rst, name, literal(r.init)
+                    );
+                    if let Some(l) = load_at("            ") {
+                        s.push_str(&format!("        elsif rising_edge({}) then\n{}", clk, l));
+                    }
+                    s.push_str("        end if;\n");
+                    s
+                }
+            };
+
+            let sensitivity = match reset {
+                Some((rst, Reset::Async)) => format!("{}, {}", clk, rst),
+                _ => clk.clone(),
+            };
+            v.push_str(&format!(
+                "\n    process ({})\n    begin\n{}    end process;\n",
+                sensitivity, body
+            ));
+        }
+
+        for m in self.memories.iter().filter(|m| !m.writes.is_empty()) {
+            v.push_str(&format!(
+                "\n    process ({})\n    begin\n        if rising_edge({}) then\n",
+                clk, clk
+            ));
+            for w in m.writes.iter() {
+                v.push_str(&format!(
+                    "            if {} = \"1\" then\n\
+                    \x20               {}(to_integer({})) <= {};\n\
+                    \x20           end if;\n",
+                    root(&w.enable),
+                    net.name(Role::Memory, m.name),
+                    num("unsigned", root(&w.addr)),
+                    root(&w.data)
+                ));
+            }
+            v.push_str("        end if;\n    end process;\n");
+        }
+
+        v.push_str("end architecture rtl;\n");
+        Ok(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::{mux, Imm, OpWrapper};
+    use crate::seq::{Input, Register, Simulator};
+    use crate::w;
+    use crate::wires::Wire;
+
+    type W8 = Wire<{ 8 }, { 1 }>;
+
+    #[test]
+    fn counter() {
+        let en: Input<{ 1 }> = Input::new("en", w!(1#1));
+        let rst: Input<{ 1 }> = Input::new("rst", w!(1#0));
+        let cnt: Register<(), { 8 }> = Register::new("cnt", w!(8#0));
+
+        let one: OpWrapper<Imm<W8>> = w!(8#1).into();
+        let ff: OpWrapper<Imm<W8>> = w!(8#0xFF).into();
+        let _ = cnt
+            .next((cnt.out() + one).label("inc"))
+            .enable_when(en.out())
+            .reset_when(rst.out(), Reset::Sync);
+
+        let mut sim = Simulator::new(());
+        let _ = sim.add_clocked(cnt.clone()).add_input(en).add_input(rst).probe("full", cnt.out().eq(ff));
+
+        assert_eq!(
+            sim.design().to_vhdl("counter").unwrap(),
+            "library ieee;\n\
+            use ieee.std_logic_1164.all;\n\
+            use ieee.numeric_std.all;\n\
+            \n\
+            entity counter is\n\
+            \x20   port (\n\
+            \x20       clk : in std_logic;\n\
+            \x20       en : in std_logic_vector(0 downto 0);\n\
+            \x20       rst : in std_logic_vector(0 downto 0);\n\
+            \x20       full : out std_logic_vector(0 downto 0)\n\
+            \x20   );\n\
+            end entity counter;\n\
+            \n\
+            architecture rtl of counter is\n\
+            \x20   signal cnt : std_logic_vector(7 downto 0) := \"00000000\";\n\
+            \x20   signal full_n2 : std_logic_vector(0 downto 0);\n\
+            \x20   signal inc : std_logic_vector(7 downto 0);\n\
+            begin\n\
+            \x20   full_n2 <= \"1\" when cnt = \"11111111\" else \"0\";\n\
+            \x20   inc <= std_logic_vector(unsigned(cnt) + unsigned'(\"00000001\"));\n\
+            \x20   full <= full_n2;\n\
+            \n\
+            \x20   process (clk)\n\
+            \x20   begin\n\
+            \x20       if rising_edge(clk) then\n\
+            \x20           if rst = \"1\" then\n\
+            \x20               cnt <= \"00000000\";\n\
+            \x20           else\n\
+            \x20               if en = \"1\" then\n\
+            \x20                   cnt <= inc;\n\
+            \x20               end if;\n\
+            \x20           end if;\n\
+            \x20       end if;\n\
+            \x20   end process;\n\
+            end architecture rtl;\n"
+        );
+    }
+
+    #[test]
+    fn names() {
+        let upper: Input<{ 4 }> = Input::new("A", w!(4#0));
+        let lower: Input<{ 4 }> = Input::new("a", w!(4#0));
+
+        let mut sim = Simulator::new(());
+        let _ = sim
+            .add_input(upper.clone())
+            .add_input(lower.clone())
+            .probe("signal", (upper.out() & lower.out()).label("and__"))
+            .probe("Signal", (upper.out() | lower.out()).label("AND"));
+
+        assert_eq!(
+            sim.design().to_vhdl("rtl").unwrap(),
+            "library ieee;\n\
+            use ieee.std_logic_1164.all;\n\
+            use ieee.numeric_std.all;\n\
+            \n\
+            entity rtl_1 is\n\
+            \x20   port (\n\
+            \x20       A : in std_logic_vector(3 downto 0);\n\
+            \x20       a_1 : in std_logic_vector(3 downto 0);\n\
+            \x20       signal_1 : out std_logic_vector(3 downto 0);\n\
+            \x20       Signal_2 : out std_logic_vector(3 downto 0)\n\
+            \x20   );\n\
+            end entity rtl_1;\n\
+            \n\
+            architecture rtl of rtl_1 is\n\
+            \x20   signal and_1 : std_logic_vector(3 downto 0);\n\
+            \x20   signal AND_2 : std_logic_vector(3 downto 0);\n\
+            begin\n\
+            \x20   and_1 <= A and a_1;\n\
+            \x20   AND_2 <= A or a_1;\n\
+            \x20   signal_1 <= and_1;\n\
+            \x20   Signal_2 <= AND_2;\n\
+            end architecture rtl;\n"
+        );
+    }
+
+    #[test]
+    fn async_reset_and_mux() {
+        let s: Input<{ 1 }> = Input::new("s", w!(1#0));
+        let a: Input<{ 4 }> = Input::new("a", w!(4#0));
+        let rst: Input<{ 1 }> = Input::new("rst", w!(1#0));
+        let r: Register<(), { 4 }> = Register::new("r", w!(4#5));
+
+        let _ = r.next(mux(s.out(), a.out(), r.out())).reset_when(rst.out(), Reset::Async);
+
+        let mut sim = Simulator::new(());
+        let _ = sim.add_clocked(r.clone()).add_input(s).add_input(a).add_input(rst);
+
+        assert_eq!(
+            sim.design().to_vhdl("hold").unwrap(),
+            "library ieee;\n\
+            use ieee.std_logic_1164.all;\n\
+            use ieee.numeric_std.all;\n\
+            \n\
+            entity hold is\n\
+            \x20   port (\n\
+            \x20       clk : in std_logic;\n\
+            \x20       s : in std_logic_vector(0 downto 0);\n\
+            \x20       a : in std_logic_vector(3 downto 0);\n\
+            \x20       rst : in std_logic_vector(0 downto 0)\n\
+            \x20   );\n\
+            end entity hold;\n\
+            \n\
+            architecture rtl of hold is\n\
+            \x20   signal r : std_logic_vector(3 downto 0) := \"0101\";\n\
+            \x20   signal r_next_n3 : std_logic_vector(3 downto 0);\n\
+            begin\n\
+            \x20   r_next_n3 <= a when s = \"1\" else r;\n\
+            \n\
+            \x20   process (clk, rst)\n\
+            \x20   begin\n\
+            \x20       if rst = \"1\" then\n\
+            \x20           r <= \"0101\";\n\
+            \x20       elsif rising_edge(clk) then\n\
+            \x20           r <= r_next_n3;\n\
+            \x20       end if;\n\
+            \x20   end process;\n\
+            end architecture rtl;\n"
+        );
+    }
+}
@@ -42,6 +42,10 @@
 #[macro_use]
 extern crate static_assertions;
 
+#[cfg(feature = "alloc")]
+#[macro_use]
+extern crate alloc;
+
 // Some old notes:
 
 // The macro way:
@@ -80,9 +84,17 @@ extern crate static_assertions;
 // let f: Or<Add<Zext<Xor<Imm<Output = [Bit; 4]>, Imm<Output = [Bit; 4]>, Output = [Bit; 4]>, Output = [Bit; 8]>, Zext<Imm<Output = [Bit; 4]>, Output = [Bit; 8]>>, Imm<Output = [Bit; 8]> = (zext::<8>(a ^ b) + zext::<8>(c)) | d;
 
 mod util;
+pub mod bits;
+#[cfg(feature = "alloc")]
+pub mod export;
 pub mod ops;
+#[cfg(feature = "alloc")]
+pub mod seq;
+pub mod state;
 pub mod wires;
 
+pub use design_macros::design;
+
 // use core::cell::RefCell;
 // use core::cell::Cell;
 // use core::ops::{Add as AddOp, Sub as SubOp, Mul as MulOp, Div as DivOp, BitAnd, BitOr, BitXor};
@@ -103,109 +115,4 @@ pub mod wires;
 // }
 
 
-// #[derive(Debug)]
-// struct RegisterInput<R: Registers, T: Display, F: Fn(&R) -> T> {
-//     func: F,
-//     _output: PhantomData<T>,
-//     _input: PhantomData<R>,
-// }
-
-// impl<R: Ctx, T: Display, F: Fn(&R) ->T> RegisterInput<R, T, F> {
-//     fn new(func: F) -> OpWrapper<R, Self> {
-//         RegisterInput { func, _input: PhantomData, _output: PhantomData }.into()
-//     }
-// }
-
-
-// impl<S: Ctx, T: Display, F: Fn(&S) ->T> Op<S> for RegisterInput<S, T, F>
-// {
-//     type Output = T;
-//     const OPERATION: OpType = OpType::RegisterInput;
-
-//     fn execute(self, state: &S) -> Self::Output {
-//         (self.func)(state)
-//     }
-
-//     fn execute_with_metadata(self, state: &S) -> (String, Self::Output) {
-//         let val = self.execute(state);
-//         (format!("<{}>", val), val)
-//     }
-// }
-
-// trait Registers {
-//     fn read_reg(&self, num: u8) -> u16;
-
-//     fn write_reg(&self, num: u8, bytes: u16);
-// }
-
-// trait Memory {
-//     fn read_mem(&self, addr: u16) -> u8;
-
-//     fn write_mem(&self, addr: u16, byte: u8);
-// }
-
-// trait Ctx: Registers + Memory {}
-// impl<S: Registers + Memory> Ctx for S {}
-
-// struct State {
-//     general: Cell<[u16; 8]>,
-//     mem: RefCell<[u8; std::u16::MAX as usize]>
-// }
-
-// impl State {
-//     fn new() -> Self {
-//         State {
-//             general: Cell::new([0u16; 8]),
-//             mem: RefCell::new([0u8; std::u16::MAX as usize])
-//         }
-//     }
-// }
-
-// impl Registers for State {
-//     fn read_reg(&self, num: u8) -> u16 {
-//         self.general.get()[num as usize]
-//     }
-
-//     fn write_reg(&self, num: u8, bytes: u16) {
-//         let mut regs = self.general.get();
-//         regs[num as usize] = bytes;
-//         self.general.set(regs);
-//     }
-// }
-
-// impl Memory for State {
-//     fn read_mem(&self, addr: u16) -> u8 {
-//         self.mem.borrow()[addr as usize]
-//     }
-
-//     fn write_mem(&self, addr: u16, byte: u8) {
-//         self.mem.borrow_mut()[addr as usize] = byte;
-//     }
-// }
-
-
-// fn main() {
-//     // let a: Imm<_> = 89.into();
-//     // let b: Imm<_> = 90.into();
-
-//     let state = State::new();
-
-//     let r0 = RegisterInput::<State, _, _>::new(|r| r.read_reg(0));
-
-//     <State as Registers>::write_reg(&state, 0, 3);
-
-//     let a: OpWrapper<_, _> = 89.into();
-//     let b = 90.into();
-
-//     let c = 678.into();
-
-//     // let w: OpWrapper<_> = a.into();
-//     let c = a + b + c + r0;
-
-//     let (m, v) = c.execute_with_metadata(&state);
-
-//     println!("{} = {}", m, v);
-// }
-
-
 // TODO: split wires into it's own hdl-wires crate (probably)
@@ -1,51 +1,70 @@
-use super::{Op, OpKind, OpWrapper};
+use super::{Exit, Op, OpKind, OpWrapper, SameWidth, Trace};
 
 use core::ops::Add as AddOp;
 
 #[derive(Copy, Clone, Debug)]
-pub struct Add<L: Op, R: Op>
-where
-    L: Op<Output = <R as Op>::Output>,
-    L::Output: AddOp<<R as Op>::Output, Output = <R as Op>::Output>
-{
+pub struct Add<L, R> {
     lhs: L,
     rhs: R,
 }
 
-impl<L: Op, R: Op> Op for Add<L, R>
+impl<S, L: Op<S>, R: Op<S>> Op<S> for Add<L, R>
 where
-    L: Op<Output = <R as Op>::Output>,
-    L::Output: AddOp<<R as Op>::Output, Output = <R as Op>::Output>
+    L::Output: SameWidth<<R as Op<S>>::Output>,
+    L::Output: AddOp<Output = <L as Op<S>>::Output>,
 {
-    type Output = <L as Op>::Output;
+    type Output = <L as Op<S>>::Output;
     const OPERATION: OpKind = OpKind::Addition;
 
-    fn execute(self) -> Self::Output {
-        let lhs = self.lhs.execute();
-        let rhs = self.rhs.execute();
+    fn execute(&self, state: &S) -> Self::Output {
+        let lhs = self.lhs.execute(state);
+        let rhs = L::Output::same_width(self.rhs.execute(state));
 
         lhs + rhs
     }
 
-    fn execute_with_metadata(self) -> (String, Self::Output) {
-        let (ml, lhs) = self.lhs.execute_with_metadata();
-        let (mr, rhs) = self.rhs.execute_with_metadata();
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::Addition, None);
+
+        let lhs = self.lhs.execute_with_trace(state, trace);
+        let rhs = L::Output::same_width(self.rhs.execute_with_trace(state, trace));
+        let out = lhs + rhs;
 
-        (format!("({} + {})", ml, mr), lhs + rhs)
+        trace.exit(&Exit::new(OpKind::Addition, &out));
+        out
     }
 }
 
-impl<L: Op, R: Op> AddOp<OpWrapper<R>> for OpWrapper<L>
+impl<S, L: Op<S>, R: Op<S>> AddOp<OpWrapper<R, S>> for OpWrapper<L, S>
 where
-    L: Op<Output = <R as Op>::Output>,
-    L::Output: AddOp<<R as Op>::Output, Output = <R as Op>::Output>
+    L::Output: SameWidth<<R as Op<S>>::Output>,
+    L::Output: AddOp<Output = <L as Op<S>>::Output>,
 {
-    type Output = OpWrapper<Add<L, R>>;
+    type Output = OpWrapper<Add<L, R>, S>;
 
-    fn add(self, rhs: OpWrapper<R>) -> Self::Output {
+    fn add(self, rhs: OpWrapper<R, S>) -> Self::Output {
         Add {
             lhs: self.unwrap(),
             rhs: rhs.unwrap(),
         }.into()
     }
 }
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use super::super::imm::Imm;
+    use crate::w;
+    use crate::wires::Wire;
+
+    #[test]
+    fn wires() {
+        let a: OpWrapper<Imm<Wire<{ 8 }, { 1 }>>> = w!(8#0xF0).into();
+        let b: OpWrapper<Imm<Wire<{ 8 }, { 1 }>>> = w!(8#0x13).into();
+
+        let (m, v) = (a + b).execute_with_metadata(&());
+
+        assert_eq!(m, "(8'hf0 + 8'h13)");
+        assert_eq!(u8::from(v), 0x03);
+    }
+}
@@ -0,0 +1,51 @@
+use super::{Exit, Op, OpKind, OpWrapper, SameWidth, Trace};
+
+use core::ops::BitAnd as BitAndOp;
+
+#[derive(Copy, Clone, Debug)]
+pub struct And<L, R> {
+    lhs: L,
+    rhs: R,
+}
+
+impl<S, L: Op<S>, R: Op<S>> Op<S> for And<L, R>
+where
+    L::Output: SameWidth<<R as Op<S>>::Output>,
+    L::Output: BitAndOp<Output = <L as Op<S>>::Output>,
+{
+    type Output = <L as Op<S>>::Output;
+    const OPERATION: OpKind = OpKind::And;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        let lhs = self.lhs.execute(state);
+        let rhs = L::Output::same_width(self.rhs.execute(state));
+
+        lhs & rhs
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::And, None);
+
+        let lhs = self.lhs.execute_with_trace(state, trace);
+        let rhs = L::Output::same_width(self.rhs.execute_with_trace(state, trace));
+        let out = lhs & rhs;
+
+        trace.exit(&Exit::new(OpKind::And, &out));
+        out
+    }
+}
+
+impl<S, L: Op<S>, R: Op<S>> BitAndOp<OpWrapper<R, S>> for OpWrapper<L, S>
+where
+    L::Output: SameWidth<<R as Op<S>>::Output>,
+    L::Output: BitAndOp<Output = <L as Op<S>>::Output>,
+{
+    type Output = OpWrapper<And<L, R>, S>;
+
+    fn bitand(self, rhs: OpWrapper<R, S>) -> Self::Output {
+        And {
+            lhs: self.unwrap(),
+            rhs: rhs.unwrap(),
+        }.into()
+    }
+}
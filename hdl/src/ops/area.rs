@@ -0,0 +1,341 @@
+//! Gate counts.
+//!
+//! Every op is assigned a cost in 2-input gate equivalents by a
+//! [`CostTable`]; [`GateEquivalents`] is a reasonable default but other
+//! technology assumptions (or other notions of cost entirely) can be plugged
+//! in. [`OpGraph::area`] and [`Design::area`] add the costs up for every
+//! labeled op (including everything that feeds it) and for the whole graph or
+//! design.
+//!
+//! [`CostTable`]: trait.CostTable.html
+//! [`GateEquivalents`]: struct.GateEquivalents.html
+//! [`OpGraph::area`]: struct.OpGraph.html#method.area
+//! [`Design::area`]: ../export/struct.Design.html#method.area
+
+use super::{identical, Compared, NodeId, OpGraph, OpKind};
+use crate::export::Design;
+use crate::wires::BitCountType;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::{self, Display};
+
+/// `2^n`, saturating at `u64::MAX`.
+fn pow2(n: BitCountType) -> u64 {
+    u32::try_from(n).ok().and_then(|n| 1u64.checked_shl(n)).unwrap_or(u64::MAX)
+}
+
+/// The cost of the parts of a design.
+pub trait CostTable {
+    /// The cost of node `id` of `g`.
+    fn op(&self, g: &OpGraph, id: NodeId) -> u64;
+
+    /// The cost of a register that's `width` bits wide. Defaults to 6 gates
+    /// (a D flip-flop) per bit.
+    fn register(&self, width: BitCountType) -> u64 {
+        6 * width as u64
+    }
+
+    /// The cost of the storage of a memory; reads are costed as ops. Defaults
+    /// to a flip-flop per bit.
+    fn memory(&self, addr_width: BitCountType, data_width: BitCountType) -> u64 {
+        self.register(data_width).saturating_mul(pow2(addr_width))
+    }
+}
+
+/// Closures can be used as cost tables for ops.
+impl<F: Fn(&OpGraph, NodeId) -> u64> CostTable for F {
+    fn op(&self, g: &OpGraph, id: NodeId) -> u64 {
+        self(g, id)
+    }
+}
+
+/// Counts 2-input gates, for textbook implementations of each op (for a `W`
+/// bit wide op):
+///   - inputs, constants, and wiring (extensions, slices, concatenations) are
+///     free
+///   - bitwise ops: `W`
+///   - adders: `5W` (ripple-carry; a full adder per bit); subtracters and
+///     magnitude comparisons also invert one operand: `6W`
+///   - equality checks: `W` `xor`s and `W - 1` `or`s (and an inverter for
+///     `==`)
+///   - 2-to-1 muxes: `3W + 1`; n-to-1 muxes are a tree of `n - 1` of those
+///   - memory reads: a `2^A`-to-1 mux
+#[derive(Copy, Clone, Debug, Default)]
+pub struct GateEquivalents;
+
+impl CostTable for GateEquivalents {
+    fn op(&self, g: &OpGraph, id: NodeId) -> u64 {
+        use OpKind::*;
+
+        let node = &g[id];
+        let ops = node.operands();
+        let width = node.value().width() as u64;
+        let in_width = ops.last().map_or(0, |o| g[*o].value().width() as u64);
+        let mux = |w: u64, inputs: u64| (3 * w + 1).saturating_mul(inputs.saturating_sub(1));
+
+        match node.kind() {
+            Immediate | RegisterInput | Register | Input => 0,
+            Zext | Sext | Slice | Concat => 0,
+            And | Or | Xor | Not => width,
+            Addition => 5 * width,
+            Subtraction => 6 * width,
+            Ult | Ule | Slt | Sle => 6 * in_width,
+            Eq => 2 * in_width,
+            Ne => 2 * in_width - 1,
+            Mux => mux(width, 2),
+            MuxN => mux(width, ops.len() as u64 - 1),
+            MemoryInput | MemoryRead => mux(width, pow2(g[ops[0]].value().width())),
+        }
+    }
+}
+
+/// One line of an [`AreaReport`](struct.AreaReport.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AreaRow {
+    /// The label of the op (or the name of the state element).
+    pub name: String,
+    /// How many ops went into the total; 0 for state elements.
+    pub nodes: usize,
+    /// The total cost.
+    pub gates: u64,
+}
+
+/// The cost of a graph or design, broken down by label.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AreaReport {
+    /// A row for every labeled op (counting the op and everything that feeds
+    /// it, including other labeled ops) and every state element.
+    pub rows: Vec<AreaRow>,
+    /// How many ops there are in total.
+    pub nodes: usize,
+    /// The total cost.
+    pub gates: u64,
+}
+
+impl AreaReport {
+    fn new() -> Self {
+        Self { rows: Vec::new(), nodes: 0, gates: 0 }
+    }
+
+    /// Adds the ops of `g` to the report. Labeled ops that compute the same
+    /// thing as an op with the same label in `seen` (the labeled ops that
+    /// already have rows) are taken to be the same signal; they, and anything
+    /// that only feeds them, aren't counted again.
+    fn add_graph<'g>(
+        &mut self,
+        g: &'g OpGraph,
+        costs: &(impl CostTable + ?Sized),
+        seen: &mut Seen<'g>,
+    ) {
+        let mut repeat = |id: NodeId| seen.contains(g, id);
+
+        // Everything that feeds the root, without going through repeats.
+        let mut counted = vec![false; g.len()];
+        if let Some(root) = g.root() {
+            counted[root.idx()] = true;
+        }
+        for id in g.ids().rev() {
+            if counted[id.idx()] && repeat(id) {
+                counted[id.idx()] = false;
+            } else if counted[id.idx()] {
+                for o in g[id].operands() {
+                    counted[o.idx()] = true;
+                }
+            }
+        }
+
+        for id in g.ids().filter(|id| counted[id.idx()]) {
+            self.nodes += 1;
+            self.gates += costs.op(g, id);
+
+            if let Some(label) = g[id].label() {
+                if !seen.contains(g, id) {
+                    seen.nodes.push((g, id));
+
+                    let (nodes, gates) = subtree(g, id, costs);
+                    self.rows.push(AreaRow { name: String::from(label), nodes, gates });
+                }
+            }
+        }
+    }
+}
+
+/// Labeled ops that have rows in a report.
+#[derive(Default)]
+struct Seen<'g> {
+    nodes: Vec<(&'g OpGraph, NodeId)>,
+    /// One memo per pair of graphs (by address).
+    memos: BTreeMap<(*const OpGraph, *const OpGraph), Compared>,
+}
+
+impl<'g> Seen<'g> {
+    /// Whether node `id` of `g` is labeled and computes the same thing as a
+    /// node with the same label that already has a row (as the exporters
+    /// decide which labeled ops are one signal).
+    fn contains(&mut self, g: &OpGraph, id: NodeId) -> bool {
+        let label = match g[id].label() {
+            Some(label) => label,
+            None => return false,
+        };
+
+        let memos = &mut self.memos;
+        self.nodes.iter().filter(|(o, n)| o[*n].label() == Some(label)).any(|(o, n)| {
+            let memo = memos.entry((g as *const _, *o as *const _)).or_default();
+            identical(g, id, o, *n, memo)
+        })
+    }
+}
+
+/// The number of ops in (and the cost of) `id` and everything that feeds it.
+fn subtree(g: &OpGraph, id: NodeId, costs: &(impl CostTable + ?Sized)) -> (usize, u64) {
+    let mut inside = vec![false; g.len()];
+    inside[id.idx()] = true;
+
+    let (mut nodes, mut gates) = (0, 0);
+    for i in g.ids().rev().filter(|i| i.idx() <= id.idx()) {
+        if inside[i.idx()] {
+            nodes += 1;
+            gates += costs.op(g, i);
+
+            for o in g[i].operands() {
+                inside[o.idx()] = true;
+            }
+        }
+    }
+
+    (nodes, gates)
+}
+
+impl OpGraph {
+    /// Estimates the cost of the graph (everything that feeds its root).
+    pub fn area(&self, costs: &impl CostTable) -> AreaReport {
+        let mut report = AreaReport::new();
+        report.add_graph(self, costs, &mut Seen::default());
+
+        report
+    }
+}
+
+impl Design {
+    /// Estimates the cost of the design: its logic and its state elements.
+    ///
+    /// A labeled op that shows up in more than one of the design's graphs
+    /// (i.e. an op with the same label that computes the same thing) is only
+    /// counted once, since exporters give it one signal. Different ops that
+    /// share a label are counted (and get rows) separately.
+    pub fn area(&self, costs: &impl CostTable) -> AreaReport {
+        let mut report = AreaReport::new();
+        let mut seen = Seen::default();
+
+        for (_, g) in self.graphs() {
+            report.add_graph(g, costs, &mut seen);
+        }
+
+        for r in self.registers.iter() {
+            let gates = costs.register(r.init.width());
+            report.rows.push(AreaRow { name: format!("{} (register)", r.name), nodes: 0, gates });
+            report.gates += gates;
+        }
+        for m in self.memories.iter() {
+            let gates = costs.memory(m.addr_width, m.data_width);
+            report.rows.push(AreaRow { name: format!("{} (memory)", m.name), nodes: 0, gates });
+            report.gates += gates;
+        }
+
+        report
+    }
+}
+
+impl Display for AreaReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24} {:>6} {:>10}", "name", "ops", "gates")?;
+
+        for r in self.rows.iter() {
+            writeln!(f, "{:<24} {:>6} {:>10}", r.name, r.nodes, r.gates)?;
+        }
+
+        write!(f, "{:<24} {:>6} {:>10}", "total", self.nodes, self.gates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Imm, Op, OpWrapper};
+    use crate::seq::{Input, Register, Simulator};
+    use crate::w;
+
+    type U8 = OpWrapper<Imm<u8>>;
+
+    #[test]
+    fn graph() {
+        let (a, b, c): (U8, U8, U8) = (1.into(), 2.into(), 3.into());
+        let g = ((a + b).label("sum") ^ c).label("out").execute_with_graph(&()).1;
+
+        let report = g.area(&GateEquivalents);
+        assert_eq!(report.gates, 5 * 8 + 8);
+        assert_eq!(
+            report.to_string(),
+            "name                        ops      gates\n\
+            sum                           3         40\n\
+            out                           5         48\n\
+            total                         5         48"
+        );
+
+        // Everything costs 1:
+        assert_eq!(g.area(&|_: &OpGraph, _: NodeId| 1).gates, 5);
+    }
+
+    #[test]
+    fn design() {
+        let inc: Input<{ 4 }> = Input::new("inc", w!(4#1));
+        let cnt: Register<(), { 4 }> = Register::new("cnt", w!(4#0));
+        let _ = cnt.next((cnt.out() + inc.out()).label("sum"));
+
+        let mut sim = Simulator::new(());
+        let _ = sim.add_clocked(cnt.clone()).probe("next", (cnt.out() + inc.out()).label("sum"));
+
+        // `sum` is in two graphs but is only counted once.
+        let report = sim.design().area(&GateEquivalents);
+        assert_eq!(
+            report.rows,
+            [
+                AreaRow { name: String::from("sum"), nodes: 3, gates: 20 },
+                AreaRow { name: String::from("cnt (register)"), nodes: 0, gates: 24 },
+            ]
+        );
+        assert_eq!((report.nodes, report.gates), (3, 44));
+    }
+
+    #[test]
+    fn shared_labels() {
+        let inc: Input<{ 4 }> = Input::new("inc", w!(4#1));
+        let cnt: Register<(), { 4 }> = Register::new("cnt", w!(4#0));
+        let _ = cnt.next((cnt.out() + inc.out()).label("sum"));
+
+        let mut sim = Simulator::new(());
+        let _ = sim.add_clocked(cnt.clone()).probe("next", (cnt.out() ^ inc.out()).label("sum"));
+
+        // Both ops are labeled `sum` but they aren't the same signal.
+        let report = sim.design().area(&GateEquivalents);
+        assert_eq!(
+            report.rows,
+            [
+                AreaRow { name: String::from("sum"), nodes: 3, gates: 4 },
+                AreaRow { name: String::from("sum"), nodes: 3, gates: 20 },
+                AreaRow { name: String::from("cnt (register)"), nodes: 0, gates: 24 },
+            ]
+        );
+        assert_eq!((report.nodes, report.gates), (6, 48));
+    }
+
+    #[test]
+    fn wide_memories() {
+        assert_eq!(GateEquivalents.memory(64, 8), u64::MAX);
+        assert_eq!(GateEquivalents.memory(60, 8), u64::MAX);
+        assert_eq!(GateEquivalents.memory(4, 8), 16 * 48);
+    }
+}
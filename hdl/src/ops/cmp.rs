@@ -0,0 +1,144 @@
+//! Comparisons; these produce 1-bit wires.
+//!
+//! Operands can be any (same width) value. Comparisons happen on the values'
+//! bits so the signed comparisons (`slt`, `sle`) treat the most significant
+//! bit as a sign bit regardless of what the type of the operands is.
+
+use super::{Exit, Op, OpKind, OpWrapper, SameWidth, Trace};
+use crate::bits::{Bits, Value};
+use crate::wires::Wire;
+
+use core::marker::PhantomData;
+
+/// A kind of comparison.
+pub trait Comparison {
+    /// The `OpKind` of comparisons of this kind.
+    const KIND: OpKind;
+
+    /// Compares two values of the same width.
+    fn compare(lhs: Bits, rhs: Bits) -> bool;
+}
+
+macro_rules! comparison {
+    ($(#[$meta:meta])* $name:ident, |$l:ident, $r:ident| $expr:expr) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug)]
+        pub struct $name;
+
+        impl Comparison for $name {
+            const KIND: OpKind = OpKind::$name;
+
+            fn compare($l: Bits, $r: Bits) -> bool {
+                $expr
+            }
+        }
+    };
+}
+
+comparison!(/** `==` */ Eq, |l, r| l == r);
+comparison!(/** `!=` */ Ne, |l, r| l != r);
+comparison!(/** Unsigned `<` */ Ult, |l, r| l.val() < r.val());
+comparison!(/** Unsigned `<=` */ Ule, |l, r| l.val() <= r.val());
+comparison!(/** Signed `<` */ Slt, |l, r| l.signed() < r.signed());
+comparison!(/** Signed `<=` */ Sle, |l, r| l.signed() <= r.signed());
+
+/// Compares two ops with the comparison `C`.
+#[derive(Copy, Clone, Debug)]
+pub struct Cmp<C, L, R> {
+    lhs: L,
+    rhs: R,
+    _kind: PhantomData<C>,
+}
+
+/// Turns the result of a comparison into a 1-bit wire.
+pub(crate) fn bit(b: bool) -> Wire<{ 1 }, { 1 }> {
+    let mut wire = Wire::new();
+    let _ = wire.set(b as u8);
+
+    wire
+}
+
+impl<S, C: Comparison, L: Op<S>, R: Op<S>> Op<S> for Cmp<C, L, R>
+where
+    L::Output: SameWidth<<R as Op<S>>::Output>,
+{
+    type Output = Wire<{ 1 }, { 1 }>;
+    const OPERATION: OpKind = C::KIND;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        let lhs = self.lhs.execute(state);
+        let rhs = self.rhs.execute(state);
+
+        bit(C::compare(lhs.to_bits(), rhs.to_bits()))
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(C::KIND, None);
+
+        let lhs = self.lhs.execute_with_trace(state, trace);
+        let rhs = self.rhs.execute_with_trace(state, trace);
+        let out = bit(C::compare(lhs.to_bits(), rhs.to_bits()));
+
+        trace.exit(&Exit::new(C::KIND, &out));
+        out
+    }
+}
+
+macro_rules! cmp_method {
+    ($(#[$meta:meta])* $method:ident, $kind:ident) => {
+        $(#[$meta])*
+        pub fn $method<R: Op<S>>(self, rhs: OpWrapper<R, S>) -> OpWrapper<Cmp<$kind, T, R>, S>
+        where
+            T::Output: SameWidth<<R as Op<S>>::Output>,
+        {
+            Cmp {
+                lhs: self.unwrap(),
+                rhs: rhs.unwrap(),
+                _kind: PhantomData,
+            }.into()
+        }
+    };
+}
+
+impl<S, T: Op<S>> OpWrapper<T, S> {
+    cmp_method!(/** `(self == rhs)` */ eq, Eq);
+    cmp_method!(/** `(self != rhs)` */ ne, Ne);
+    cmp_method!(/** `(self < rhs)`, unsigned */ ult, Ult);
+    cmp_method!(/** `(self <= rhs)`, unsigned */ ule, Ule);
+    cmp_method!(/** `(self < rhs)`, signed */ slt, Slt);
+    cmp_method!(/** `(self <= rhs)`, signed */ sle, Sle);
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::super::imm::Imm;
+    use super::*;
+
+    fn imm(v: u8) -> OpWrapper<Imm<u8>> {
+        v.into()
+    }
+
+    fn check(op: OpWrapper<impl Op<Output = Wire<{ 1 }, { 1 }>>>, meta: &str, val: bool) {
+        let (m, v) = op.execute_with_metadata(&());
+
+        assert_eq!(m, meta);
+        assert_eq!(u8::from(v), val as u8);
+    }
+
+    #[test]
+    fn unsigned() {
+        check(imm(3).eq(imm(3)), "(3 == 3)", true);
+        check(imm(3).ne(imm(3)), "(3 != 3)", false);
+        check(imm(3).ult(imm(200)), "(3 < 200)", true);
+        check(imm(200).ule(imm(3)), "(200 <= 3)", false);
+        check(imm(7).ule(imm(7)), "(7 <= 7)", true);
+    }
+
+    #[test]
+    fn signed() {
+        // 200 is -56 as an `i8`:
+        check(imm(200).slt(imm(3)), "(200 <s 3)", true);
+        check(imm(3).sle(imm(200)), "(3 <=s 200)", false);
+        check(imm(200).sle(imm(200)), "(200 <=s 200)", true);
+    }
+}
@@ -0,0 +1,66 @@
+use super::{Exit, Op, OpKind, OpWrapper, Trace};
+use crate::bits::{Bits, FromBits, Value};
+
+use core::marker::PhantomData;
+
+/// Puts two ops' outputs side by side (`hi` in the most significant bits),
+/// producing a `W`.
+///
+/// Shows up in metadata like Verilog concatenations: `{a, b}`.
+#[derive(Copy, Clone, Debug)]
+pub struct Concat<H, L, W> {
+    hi: H,
+    lo: L,
+    _out: PhantomData<W>,
+}
+
+fn join(hi: Bits, lo: Bits) -> Bits {
+    Bits::new(hi.width() + lo.width(), (hi.val() << lo.width()) | lo.val())
+}
+
+impl<S, H: Op<S>, L: Op<S>, W: FromBits> Op<S> for Concat<H, L, W> {
+    type Output = W;
+    const OPERATION: OpKind = OpKind::Concat;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        let hi = self.hi.execute(state).to_bits();
+        let lo = self.lo.execute(state).to_bits();
+
+        W::from_bits(join(hi, lo))
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::Concat, None);
+
+        let hi = self.hi.execute_with_trace(state, trace).to_bits();
+        let lo = self.lo.execute_with_trace(state, trace).to_bits();
+        let out = W::from_bits(join(hi, lo));
+
+        trace.exit(&Exit::new(OpKind::Concat, &out));
+        out
+    }
+}
+
+/// Makes `{hi, lo}`: a `W` with `hi` in its most significant bits and `lo` in
+/// its least significant bits.
+///
+/// As with [`slice`](struct.OpWrapper.html#method.slice), `W` must be exactly
+/// as wide as `hi` and `lo` put together; this panics if it isn't. `W` is the
+/// first generic parameter: `concat::<Wire<{ 16 }, { 2 }>, _, _, _>(a, b)`.
+pub fn concat<W: FromBits, S, H: Op<S>, L: Op<S>>(
+    hi: OpWrapper<H, S>,
+    lo: OpWrapper<L, S>,
+) -> OpWrapper<Concat<H, L, W>, S>
+where
+    H::Output: FromBits,
+    L::Output: FromBits,
+{
+    let (h, l) = (<H::Output as FromBits>::WIDTH, <L::Output as FromBits>::WIDTH);
+    assert_eq!(W::WIDTH, h + l, "concatenating {} and {} bits makes {} bits", h, l, h + l);
+
+    Concat {
+        hi: hi.unwrap(),
+        lo: lo.unwrap(),
+        _out: PhantomData,
+    }.into()
+}
@@ -0,0 +1,93 @@
+//! Graphviz output for evaluated expressions.
+//!
+//! Every node is labeled with its kind, its label (if it has one), and the
+//! value it produced. Edges go from operands to the ops that use them and are
+//! labeled with the operand's position (see [`OpData::operands`]).
+//!
+//! [`OpData::operands`]: enum.OpData.html#method.operands
+
+use super::{Op, OpGraph};
+
+use alloc::string::String;
+
+/// Executes `op` and renders the graph of everything that was evaluated as
+/// Graphviz DOT.
+pub fn to_dot<S, O: Op<S>>(op: &O, state: &S) -> String {
+    op.execute_with_graph(state).1.to_dot()
+}
+
+/// Escapes a string so it can go between double quotes in a DOT file.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+impl OpGraph {
+    /// Renders this graph as Graphviz DOT.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph ops {\n");
+
+        for id in self.ids() {
+            let node = &self[id];
+
+            let mut label = format!("{:?}", node.kind());
+            if let Some(l) = node.label() {
+                label.push_str("\\n");
+                label.push_str(&escape(l));
+            }
+            label.push_str(&format!("\\n{}", node.value()));
+
+            dot.push_str(&format!("    n{} [label=\"{}\"];\n", id.idx(), label));
+
+            for (pos, operand) in node.operands().iter().enumerate() {
+                dot.push_str(&format!(
+                    "    n{} -> n{} [label=\"{}\"];\n",
+                    operand.idx(),
+                    id.idx(),
+                    pos
+                ));
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::OpWrapper;
+    use super::super::imm::Imm;
+
+    #[test]
+    fn dot() {
+        let a: OpWrapper<Imm<u8>> = 2u8.into();
+        let b: OpWrapper<Imm<u8>> = 3u8.into();
+
+        assert_eq!(
+            to_dot(&(a + !b).label("say \"hi\""), &()),
+            "digraph ops {\n\
+             \x20   n0 [label=\"Immediate\\n2\"];\n\
+             \x20   n1 [label=\"Immediate\\n3\"];\n\
+             \x20   n2 [label=\"Not\\n252\"];\n\
+             \x20   n1 -> n2 [label=\"0\"];\n\
+             \x20   n3 [label=\"Addition\\nsay \\\"hi\\\"\\n254\"];\n\
+             \x20   n0 -> n3 [label=\"0\"];\n\
+             \x20   n2 -> n3 [label=\"1\"];\n\
+             }\n"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! Exhaustive equivalence checking.
+//!
+//! [`OpGraph::check_equivalent`] evaluates two graphs and
+//! [`OpGraph::check_against`] evaluates a graph and a reference model (a
+//! closure) for every possible combination of input values, stopping at the
+//! first combination where the two disagree. This is only practical for small
+//! designs: all of the inputs together can be at most [`MAX_INPUT_WIDTH`] bits
+//! wide.
+//!
+//! The inputs of a graph are its ports and registers (matched up by name; a
+//! port and a register with the same name are different inputs) and its reads
+//! of the register file in the context (matched up by register number). Graphs
+//! that read from memory can't be checked.
+//!
+//! [`OpGraph::check_equivalent`]: struct.OpGraph.html#method.check_equivalent
+//! [`OpGraph::check_against`]: struct.OpGraph.html#method.check_against
+//! [`MAX_INPUT_WIDTH`]: constant.MAX_INPUT_WIDTH.html
+
+use super::{OpData, OpGraph, OpGraphNode, OpKind};
+use crate::bits::{Bits, Value};
+use crate::wires::BitCountType;
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+use core::ops::Index;
+
+/// The most input bits (across all inputs) a check will enumerate.
+pub const MAX_INPUT_WIDTH: BitCountType = 24;
+
+/// An input of a graph.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Var {
+    /// A register in the register file (a `RegisterInput`), by number.
+    RegisterFile(u8),
+    /// A state element (a `Register`), by name.
+    Register(&'static str),
+    /// A port (an `Input`), by name.
+    Input(&'static str),
+}
+
+impl Var {
+    fn of(node: &OpGraphNode) -> Option<Self> {
+        match *node.data() {
+            OpData::RegisterInput { num } => Some(Var::RegisterFile(num)),
+            OpData::Register { name } => Some(Var::Register(name)),
+            OpData::Input { name } => Some(Var::Input(name)),
+            _ => None,
+        }
+    }
+}
+
+/// `r1` for the register file, `reg a` for registers, and `a` for ports.
+impl Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Var::RegisterFile(num) => write!(f, "r{}", num),
+            Var::Register(name) => write!(f, "reg {}", name),
+            Var::Input(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// A value for every input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assignment {
+    vals: Vec<(Var, Bits)>,
+}
+
+impl Assignment {
+    /// The value of the port called `name` or, if there isn't one, of the
+    /// register called `name`.
+    pub fn get(&self, name: &str) -> Option<Bits> {
+        self.named(name).copied()
+    }
+
+    /// The value of register `num` of the register file.
+    pub fn register_file(&self, num: u8) -> Option<Bits> {
+        self.var(Var::RegisterFile(num))
+    }
+
+    /// The value of `var`.
+    pub fn var(&self, var: Var) -> Option<Bits> {
+        self.vals.iter().find(|(v, _)| *v == var).map(|(_, b)| *b)
+    }
+
+    /// Every input and its value, ordered by `Var`.
+    pub fn iter(&self) -> impl Iterator<Item = (Var, Bits)> + '_ {
+        self.vals.iter().copied()
+    }
+
+    fn named(&self, name: &str) -> Option<&Bits> {
+        let find = |input: bool| {
+            self.vals.iter().find(|(v, _)| match *v {
+                Var::Input(n) => input && n == name,
+                Var::Register(n) => !input && n == name,
+                Var::RegisterFile(_) => false,
+            })
+        };
+
+        find(true).or_else(|| find(false)).map(|(_, b)| b)
+    }
+}
+
+/// Looks up ports and registers like [`get`](#method.get). Panics if there's
+/// no port or register called `name`.
+impl Index<&str> for Assignment {
+    type Output = Bits;
+
+    fn index(&self, name: &str) -> &Bits {
+        self.named(name).unwrap_or_else(|| panic!("there's no input called `{}`", name))
+    }
+}
+
+impl Display for Assignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (var, val)) in self.vals.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{} = {}", var, val.literal())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Inputs on which two expressions disagree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Counterexample {
+    /// The value of every input (of both expressions).
+    pub inputs: Assignment,
+    /// What the graph (the one the check was called on) produced.
+    pub left: Bits,
+    /// What the other graph or the model produced.
+    pub right: Bits,
+}
+
+impl Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} != {}", self.inputs, self.left.literal(), self.right.literal())
+    }
+}
+
+/// Why a check couldn't be done.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EquivError {
+    /// One of the graphs reads from memory (`MemoryInput` or `MemoryRead`).
+    Unsupported(OpKind),
+    /// The inputs are this many bits wide together, which is more than
+    /// [`MAX_INPUT_WIDTH`](constant.MAX_INPUT_WIDTH.html).
+    TooWide(BitCountType),
+    /// An input has a different width in each expression.
+    InputWidths(Var, BitCountType, BitCountType),
+    /// The expressions produce values of different widths.
+    OutputWidths(BitCountType, BitCountType),
+    /// One of the graphs has no nodes.
+    Empty,
+}
+
+impl Display for EquivError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EquivError::*;
+
+        match self {
+            Unsupported(kind) => write!(f, "`{:?}` ops can't be checked", kind),
+            TooWide(w) => {
+                let max = MAX_INPUT_WIDTH;
+                write!(f, "the inputs are {} bits wide; at most {} can be checked", w, max)
+            }
+            InputWidths(var, l, r) => {
+                write!(f, "`{}` is {} bits wide on one side and {} on the other", var, l, r)
+            }
+            OutputWidths(l, r) => write!(f, "the outputs are {} and {} bits wide", l, r),
+            Empty => write!(f, "the graph is empty"),
+        }
+    }
+}
+
+/// Adds the inputs of `g` to `vars`.
+fn vars_of(g: &OpGraph, vars: &mut BTreeMap<Var, BitCountType>) -> Result<(), EquivError> {
+    if g.is_empty() {
+        return Err(EquivError::Empty);
+    }
+
+    for node in g.nodes() {
+        if let kind @ OpKind::MemoryInput | kind @ OpKind::MemoryRead = node.kind() {
+            return Err(EquivError::Unsupported(kind));
+        }
+
+        if let Some(var) = Var::of(node) {
+            let width = node.value().width();
+
+            match *vars.entry(var).or_insert(width) {
+                w if w == width => {}
+                w => return Err(EquivError::InputWidths(var, w, width)),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Calls `f` with every assignment of values to `vars`, in order (counting up
+/// with the last input as the least significant bits), until it returns
+/// something.
+fn enumerate<R>(
+    vars: &BTreeMap<Var, BitCountType>,
+    mut f: impl FnMut(&Assignment) -> Result<Option<R>, EquivError>,
+) -> Result<Option<R>, EquivError> {
+    let total: BitCountType = vars.values().sum();
+    if total > MAX_INPUT_WIDTH {
+        return Err(EquivError::TooWide(total));
+    }
+
+    let vals = vars.iter().map(|(v, w)| (*v, Bits::new(*w, 0))).collect();
+    let mut inputs = Assignment { vals };
+
+    for combination in 0..(1u128 << total) {
+        let mut rest = combination;
+        for (_, val) in inputs.vals.iter_mut().rev() {
+            *val = Bits::new(val.width(), rest);
+            rest >>= val.width();
+        }
+
+        if let Some(r) = f(&inputs)? {
+            return Ok(Some(r));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The output of `g` for `inputs`.
+fn output(g: &OpGraph, inputs: &Assignment) -> Bits {
+    let vals = g.evaluate(|node, _| {
+        Var::of(node).and_then(|v| inputs.var(v)).expect("every input has a value")
+    });
+
+    vals[g.root().expect("the graph to not be empty").idx()]
+}
+
+impl OpGraph {
+    /// Checks whether `self` and `other` produce the same output for every
+    /// combination of input values. Returns the first combination for which
+    /// they don't, if there is one.
+    ///
+    /// Inputs that only one of the graphs reads are still enumerated.
+    pub fn check_equivalent(&self, other: &OpGraph) -> Result<Option<Counterexample>, EquivError> {
+        let mut vars = BTreeMap::new();
+        vars_of(self, &mut vars)?;
+        vars_of(other, &mut vars)?;
+
+        // `vars_of` has already checked that neither graph is empty.
+        let (l, r) = (self[self.root().unwrap()].value(), other[other.root().unwrap()].value());
+        if l.width() != r.width() {
+            return Err(EquivError::OutputWidths(l.width(), r.width()));
+        }
+
+        enumerate(&vars, |inputs| {
+            let (left, right) = (output(self, inputs), output(other, inputs));
+
+            Ok(if left == right {
+                None
+            } else {
+                Some(Counterexample { inputs: inputs.clone(), left, right })
+            })
+        })
+    }
+
+    /// Checks `self` against a reference model: `model` is given the value of
+    /// every input and has to produce the value `self` should have.
+    /// Returns the first combination of inputs for which they disagree, if
+    /// there is one.
+    pub fn check_against<V: Value>(
+        &self,
+        mut model: impl FnMut(&Assignment) -> V,
+    ) -> Result<Option<Counterexample>, EquivError> {
+        let mut vars = BTreeMap::new();
+        vars_of(self, &mut vars)?;
+
+        enumerate(&vars, |inputs| {
+            let (left, right) = (output(self, inputs), model(inputs).to_bits());
+
+            if left.width() != right.width() {
+                Err(EquivError::OutputWidths(left.width(), right.width()))
+            } else if left == right {
+                Ok(None)
+            } else {
+                Ok(Some(Counterexample { inputs: inputs.clone(), left, right }))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Op;
+    use crate::seq::{Input, Register};
+    use crate::w;
+
+    fn ports() -> (Input<{ 4 }>, Input<{ 4 }>) {
+        (Input::new("a", w!(4#0)), Input::new("b", w!(4#0)))
+    }
+
+    fn graph(op: impl Op) -> OpGraph {
+        op.execute_with_graph(&()).1
+    }
+
+    #[test]
+    fn graphs() {
+        let (a, b) = ports();
+
+        let sum = graph(a.out() + b.out());
+        assert_eq!(sum.check_equivalent(&graph(b.out() + a.out())), Ok(None));
+
+        // a - b is a + !b + 1, not a + !b:
+        let diff = graph(a.out() - b.out());
+        let cex = diff.check_equivalent(&graph(a.out() + !b.out())).unwrap().unwrap();
+        assert_eq!(cex.to_string(), "a = 4'h0, b = 4'h0: 4'h0 != 4'hf");
+        assert_eq!((cex.inputs["a"], cex.inputs.get("b")), (Bits::new(4, 0), Some(Bits::new(4, 0))));
+
+        // Inputs only one side reads still count:
+        let cex = sum.check_equivalent(&graph(a.out() + a.out())).unwrap().unwrap();
+        assert_eq!(cex.to_string(), "a = 4'h0, b = 4'h1: 4'h1 != 4'h0");
+    }
+
+    #[test]
+    fn model() {
+        let (a, b) = ports();
+        let g = graph(a.out() + b.out());
+
+        assert_eq!(g.check_against(|i| i["a"] + i["b"]), Ok(None));
+
+        // Off by one once the sum overflows:
+        let cex = g.check_against(|i| Bits::new(4, (i["a"].val() + i["b"].val()).min(15))).unwrap().unwrap();
+        assert_eq!((cex.inputs["a"].val(), cex.inputs["b"].val()), (1, 15));
+        assert_eq!((cex.left.val(), cex.right.val()), (0, 15));
+
+        assert_eq!(g.check_against(|_| 0u8), Err(EquivError::OutputWidths(4, 8)));
+    }
+
+    #[test]
+    fn registers_and_ports() {
+        let (a, _) = ports();
+        let r: Register<(), { 4 }> = Register::new("a", w!(4#0));
+
+        // The register and the port are different inputs, even though they're
+        // both called `a`:
+        let g = graph(r.out() + a.out());
+        assert_eq!(g.check_against(|i| i.var(Var::Register("a")).unwrap() + i["a"]), Ok(None));
+
+        let cex = g.check_against(|i| i["a"]).unwrap().unwrap();
+        assert_eq!(cex.to_string(), "reg a = 4'h1, a = 4'h0: 4'h1 != 4'h0");
+    }
+
+    #[test]
+    fn errors() {
+        let (a, _) = ports();
+        let wide: Input<{ 8 }> = Input::new("a", w!(8#0));
+
+        let g = graph(wide.out() + wide.out());
+        assert_eq!(g.check_against(|i| i["a"] + i["a"]), Ok(None));
+        assert_eq!(
+            g.check_equivalent(&graph(a.out())),
+            Err(EquivError::InputWidths(Var::Input("a"), 8, 4))
+        );
+        assert_eq!(g.check_against(|_| 0u16), Err(EquivError::OutputWidths(8, 16)));
+
+        // Too many inputs are caught before anything is evaluated:
+        let (hi, lo): (Input<{ 16 }>, Input<{ 16 }>) =
+            (Input::new("hi", w!(16#0)), Input::new("lo", w!(16#0)));
+        let g = graph(hi.out() ^ lo.out());
+        assert_eq!(g.check_against(|_| 0u16), Err(EquivError::TooWide(32)));
+
+        let mut mem = OpGraph::new();
+        let addr = mem.push(OpData::Immediate(), Bits::new(4, 0));
+        let _ = mem.push(OpData::MemoryRead { name: "ram", addr }, Bits::new(8, 0));
+        assert_eq!(mem.check_against(|_| 0u8), Err(EquivError::Unsupported(OpKind::MemoryRead)));
+
+        assert_eq!(OpGraph::new().check_against(|_| 0u8), Err(EquivError::Empty));
+    }
+}
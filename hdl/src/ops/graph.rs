@@ -0,0 +1,510 @@
+//! Evaluated expressions, as data.
+//!
+//! [`Op::execute_with_graph`] hands back an [`OpGraph`] (built by a
+//! [`GraphBuilder`] trace): an owned copy of the expression that was run
+//! where every node remembers what kind of op it was, which nodes fed it, and
+//! the value it produced. Unlike the op types themselves these aren't generic
+//! so they're easy to walk over, print, and analyze.
+//!
+//! Nodes live in a `Vec` and refer to their operands by index
+//! ([`NodeId`]). Operands are always pushed before the nodes that use them so
+//! the nodes are always in topological order.
+//!
+//! [`Op::execute_with_graph`]: trait.Op.html#method.execute_with_graph
+//! [`OpGraph`]: struct.OpGraph.html
+//! [`GraphBuilder`]: struct.GraphBuilder.html
+//! [`NodeId`]: struct.NodeId.html
+
+use super::{Exit, OpKind, Param, Trace};
+use crate::bits::Bits;
+use crate::wires::BitCountType;
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::ops::Index;
+
+/// Refers to a node within an [`OpGraph`](struct.OpGraph.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// The position of the node in [`OpGraph::nodes`](struct.OpGraph.html#method.nodes).
+    pub fn idx(self) -> usize {
+        self.0
+    }
+}
+
+/// The parts of a node that are specific to its kind: its operands and any
+/// parameters.
+///
+/// Until https://github.com/rust-lang/rfcs/pull/2593 happens, we're going to
+/// have to keep the trait impls, [`OpKind`](enum.OpKind.html), and this enum in
+/// sync manually.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OpData {
+    Immediate(),
+    RegisterInput { num: u8 },
+    MemoryInput { addr: NodeId },
+    Addition { lhs: NodeId, rhs: NodeId },
+    Subtraction { lhs: NodeId, rhs: NodeId },
+    And { lhs: NodeId, rhs: NodeId },
+    Or { lhs: NodeId, rhs: NodeId },
+    Xor { lhs: NodeId, rhs: NodeId },
+    Not { inp: NodeId },
+    Mux { sel: NodeId, a: NodeId, b: NodeId },
+    MuxN { sel: NodeId, inputs: Vec<NodeId> },
+    Zext { inp: NodeId },
+    Sext { inp: NodeId },
+    Eq { lhs: NodeId, rhs: NodeId },
+    Ne { lhs: NodeId, rhs: NodeId },
+    Ult { lhs: NodeId, rhs: NodeId },
+    Ule { lhs: NodeId, rhs: NodeId },
+    Slt { lhs: NodeId, rhs: NodeId },
+    Sle { lhs: NodeId, rhs: NodeId },
+    Slice { inp: NodeId, hi: BitCountType, lo: BitCountType },
+    Concat { hi: NodeId, lo: NodeId },
+    Register { name: &'static str },
+    Input { name: &'static str },
+    MemoryRead { name: &'static str, addr: NodeId },
+}
+
+impl OpData {
+    /// The `OpKind` this data belongs to.
+    pub fn kind(&self) -> OpKind {
+        use OpData::*;
+
+        match self {
+            Immediate() => OpKind::Immediate,
+            RegisterInput { .. } => OpKind::RegisterInput,
+            MemoryInput { .. } => OpKind::MemoryInput,
+            Addition { .. } => OpKind::Addition,
+            Subtraction { .. } => OpKind::Subtraction,
+            And { .. } => OpKind::And,
+            Or { .. } => OpKind::Or,
+            Xor { .. } => OpKind::Xor,
+            Not { .. } => OpKind::Not,
+            Mux { .. } => OpKind::Mux,
+            MuxN { .. } => OpKind::MuxN,
+            Zext { .. } => OpKind::Zext,
+            Sext { .. } => OpKind::Sext,
+            Eq { .. } => OpKind::Eq,
+            Ne { .. } => OpKind::Ne,
+            Ult { .. } => OpKind::Ult,
+            Ule { .. } => OpKind::Ule,
+            Slt { .. } => OpKind::Slt,
+            Sle { .. } => OpKind::Sle,
+            Slice { .. } => OpKind::Slice,
+            Concat { .. } => OpKind::Concat,
+            Register { .. } => OpKind::Register,
+            Input { .. } => OpKind::Input,
+            MemoryRead { .. } => OpKind::MemoryRead,
+        }
+    }
+
+    /// Puts together the `OpData` for an op of kind `kind` from the ids of its
+    /// operands.
+    fn from_operands(kind: OpKind, param: Param, ops: &[NodeId]) -> Self {
+        use OpData::*;
+
+        match kind {
+            OpKind::Immediate => Immediate(),
+            OpKind::RegisterInput => match param {
+                Param::Register(num) => RegisterInput { num },
+                p => unreachable!("register inputs to have a register number; got {:?}", p),
+            },
+            OpKind::MemoryInput => MemoryInput { addr: ops[0] },
+            OpKind::Addition => Addition { lhs: ops[0], rhs: ops[1] },
+            OpKind::Subtraction => Subtraction { lhs: ops[0], rhs: ops[1] },
+            OpKind::And => And { lhs: ops[0], rhs: ops[1] },
+            OpKind::Or => Or { lhs: ops[0], rhs: ops[1] },
+            OpKind::Xor => Xor { lhs: ops[0], rhs: ops[1] },
+            OpKind::Not => Not { inp: ops[0] },
+            OpKind::Mux => Mux { sel: ops[0], a: ops[1], b: ops[2] },
+            OpKind::MuxN => MuxN { sel: ops[0], inputs: ops[1..].to_vec() },
+            OpKind::Zext => Zext { inp: ops[0] },
+            OpKind::Sext => Sext { inp: ops[0] },
+            OpKind::Eq => Eq { lhs: ops[0], rhs: ops[1] },
+            OpKind::Ne => Ne { lhs: ops[0], rhs: ops[1] },
+            OpKind::Ult => Ult { lhs: ops[0], rhs: ops[1] },
+            OpKind::Ule => Ule { lhs: ops[0], rhs: ops[1] },
+            OpKind::Slt => Slt { lhs: ops[0], rhs: ops[1] },
+            OpKind::Sle => Sle { lhs: ops[0], rhs: ops[1] },
+            OpKind::Slice => match param {
+                Param::Range { hi, lo } => Slice { inp: ops[0], hi, lo },
+                p => unreachable!("slices to have a range; got {:?}", p),
+            },
+            OpKind::Concat => Concat { hi: ops[0], lo: ops[1] },
+            OpKind::Register => match param {
+                Param::Name(name) => Register { name },
+                p => unreachable!("registers to have a name; got {:?}", p),
+            },
+            OpKind::Input => match param {
+                Param::Name(name) => Input { name },
+                p => unreachable!("inputs to have a name; got {:?}", p),
+            },
+            OpKind::MemoryRead => match param {
+                Param::Name(name) => MemoryRead { name, addr: ops[0] },
+                p => unreachable!("memory reads to have a name; got {:?}", p),
+            },
+        }
+    }
+
+    /// A copy of this data with its operands swapped out for `ops`.
+    pub(super) fn with_operands(&self, ops: &[NodeId]) -> Self {
+        Self::from_operands(self.kind(), self.param(), ops)
+    }
+
+    /// The parameters of this op.
+    pub fn param(&self) -> Param {
+        match *self {
+            OpData::RegisterInput { num } => Param::Register(num),
+            OpData::Slice { hi, lo, .. } => Param::Range { hi, lo },
+            OpData::Register { name } | OpData::Input { name } |
+            OpData::MemoryRead { name, .. } => Param::Name(name),
+            _ => Param::None,
+        }
+    }
+
+    /// The nodes that feed this one, in operand order (i.e. `lhs` then `rhs`;
+    /// the select line first for muxes).
+    pub fn operands(&self) -> Vec<NodeId> {
+        use OpData::*;
+
+        match self {
+            Immediate() | RegisterInput { .. } | Register { .. } | Input { .. } => Vec::new(),
+            MemoryInput { addr } | MemoryRead { addr, .. } => vec![*addr],
+            Addition { lhs, rhs } | Subtraction { lhs, rhs } |
+            And { lhs, rhs } | Or { lhs, rhs } | Xor { lhs, rhs } |
+            Eq { lhs, rhs } | Ne { lhs, rhs } | Ult { lhs, rhs } |
+            Ule { lhs, rhs } | Slt { lhs, rhs } | Sle { lhs, rhs } => vec![*lhs, *rhs],
+            Not { inp } | Zext { inp } | Sext { inp } | Slice { inp, .. } => vec![*inp],
+            Concat { hi, lo } => vec![*hi, *lo],
+            Mux { sel, a, b } => vec![*sel, *a, *b],
+            MuxN { sel, inputs } => {
+                let mut ops = Vec::with_capacity(inputs.len() + 1);
+                ops.push(*sel);
+                ops.extend_from_slice(inputs);
+                ops
+            }
+        }
+    }
+}
+
+/// A single evaluated op.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpGraphNode {
+    kind: OpKind,
+    data: OpData,
+    label: Option<&'static str>,
+    value: Bits,
+}
+
+impl OpGraphNode {
+    /// What kind of op this node is.
+    pub fn kind(&self) -> OpKind {
+        self.kind
+    }
+
+    /// This node's operands and parameters.
+    pub fn data(&self) -> &OpData {
+        &self.data
+    }
+
+    /// The nodes that feed this one; see [`OpData::operands`](enum.OpData.html#method.operands).
+    pub fn operands(&self) -> Vec<NodeId> {
+        self.data.operands()
+    }
+
+    /// The name given to this op with
+    /// [`OpWrapper::label`](struct.OpWrapper.html#method.label), if any.
+    pub fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    /// The value this op produced.
+    pub fn value(&self) -> Bits {
+        self.value
+    }
+
+    /// Computes this op's value from the values of its operands (in operand
+    /// order).
+    ///
+    /// Returns `None` for inputs since their values come from outside of the
+    /// graph. Immediates produce the value they were recorded with.
+    pub fn apply(&self, ops: &[Bits]) -> Option<Bits> {
+        use OpData::*;
+
+        Some(match self.data {
+            Immediate() => self.value,
+            RegisterInput { .. } | MemoryInput { .. } | Register { .. } | Input { .. } |
+            MemoryRead { .. } => return None,
+            Addition { .. } => ops[0] + ops[1],
+            Subtraction { .. } => ops[0] - ops[1],
+            And { .. } => ops[0] & ops[1],
+            Or { .. } => ops[0] | ops[1],
+            Xor { .. } => ops[0] ^ ops[1],
+            Not { .. } => !ops[0],
+            Mux { .. } => if ops[0].val() == 1 { ops[1] } else { ops[2] },
+            MuxN { .. } => ops[1 + ops[0].val() as usize],
+            Zext { .. } => ops[0].zext(self.value.width()),
+            Sext { .. } => ops[0].sext(self.value.width()),
+            Eq { .. } => Bits::new(1, (ops[0] == ops[1]) as u128),
+            Ne { .. } => Bits::new(1, (ops[0] != ops[1]) as u128),
+            Ult { .. } => Bits::new(1, (ops[0].val() < ops[1].val()) as u128),
+            Ule { .. } => Bits::new(1, (ops[0].val() <= ops[1].val()) as u128),
+            Slt { .. } => Bits::new(1, (ops[0].signed() < ops[1].signed()) as u128),
+            Sle { .. } => Bits::new(1, (ops[0].signed() <= ops[1].signed()) as u128),
+            Slice { hi, lo, .. } => Bits::new(hi - lo + 1, ops[0].val() >> lo),
+            Concat { .. } => {
+                let (hi, lo) = (ops[0], ops[1]);
+                Bits::new(hi.width() + lo.width(), (hi.val() << lo.width()) | lo.val())
+            }
+        })
+    }
+}
+
+/// An evaluated expression.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpGraph {
+    nodes: Vec<OpGraphNode>,
+}
+
+impl OpGraph {
+    /// An empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node to the graph. All of the node's operands must already be in
+    /// the graph.
+    pub fn push(&mut self, data: OpData, value: Bits) -> NodeId {
+        debug_assert!(data.operands().iter().all(|o| o.0 < self.nodes.len()));
+
+        self.nodes.push(OpGraphNode { kind: data.kind(), data, label: None, value });
+        NodeId(self.nodes.len() - 1)
+    }
+
+    /// Names a node. If the node already has a label it's replaced (i.e. the
+    /// outermost label wins).
+    pub fn set_label(&mut self, id: NodeId, label: &'static str) {
+        self.nodes[id.0].label = Some(label);
+    }
+
+    /// Every node in the graph, operands before users.
+    pub fn nodes(&self) -> &[OpGraphNode] {
+        &self.nodes
+    }
+
+    /// The ids of every node in the graph, operands before users.
+    pub fn ids(&self) -> impl DoubleEndedIterator<Item = NodeId> {
+        (0..self.nodes.len()).map(NodeId)
+    }
+
+    /// The node that produced the expression's output (the last node that was
+    /// added), if there is one.
+    pub fn root(&self) -> Option<NodeId> {
+        self.nodes.len().checked_sub(1).map(NodeId)
+    }
+
+    /// Evaluates the graph again, returning the value of every node.
+    ///
+    /// `input` is asked for the values of inputs (register and memory reads and
+    /// the outputs of state elements, memories, and ports);
+    /// it's given the input's node and the values of the input's operands
+    /// (i.e. the address, for memory reads). To replay the graph as it was
+    /// recorded, return `node.value()`.
+    pub fn evaluate(&self, mut input: impl FnMut(&OpGraphNode, &[Bits]) -> Bits) -> Vec<Bits> {
+        let mut vals: Vec<Bits> = Vec::with_capacity(self.nodes.len());
+
+        for node in self.nodes.iter() {
+            let ops: Vec<Bits> = node.operands().iter().map(|o| vals[o.0]).collect();
+            let val = node.apply(&ops).unwrap_or_else(|| input(node, &ops));
+
+            vals.push(val);
+        }
+
+        vals
+    }
+
+    /// The number of nodes in the graph.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the graph has any nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl Index<NodeId> for OpGraph {
+    type Output = OpGraphNode;
+
+    fn index(&self, id: NodeId) -> &OpGraphNode {
+        &self.nodes[id.0]
+    }
+}
+
+/// Pairs of nodes that [`identical`] has already compared; without this,
+/// comparing expressions that share subexpressions takes exponential time.
+pub(crate) type Compared = BTreeMap<(NodeId, NodeId), bool>;
+
+/// Whether node `a` of `ga` and node `b` of `gb` compute the same thing (given
+/// the same inputs), i.e. are the same kind of op on the same operands. The
+/// graphs can be the same graph.
+///
+/// `memo` has to be specific to the pair of graphs.
+pub(crate) fn identical(
+    ga: &OpGraph,
+    a: NodeId,
+    gb: &OpGraph,
+    b: NodeId,
+    memo: &mut Compared,
+) -> bool {
+    if core::ptr::eq(ga, gb) && a == b {
+        return true;
+    }
+    if let Some(same) = memo.get(&(a, b)) {
+        return *same;
+    }
+
+    let (na, nb) = (&ga[a], &gb[b]);
+    let params = na.kind() == nb.kind()
+        && na.value().width() == nb.value().width()
+        && na.data().param() == nb.data().param()
+        && (na.kind() != OpKind::Immediate || na.value() == nb.value());
+
+    let (oa, ob) = (na.operands(), nb.operands());
+    let same = params
+        && oa.len() == ob.len()
+        && oa.iter().zip(ob.iter()).all(|(x, y)| identical(ga, *x, gb, *y, memo));
+
+    let _ = memo.insert((a, b), same);
+    same
+}
+
+/// A trace that records an [`OpGraph`](struct.OpGraph.html).
+#[derive(Debug, Default)]
+pub struct GraphBuilder {
+    graph: OpGraph,
+    stack: Vec<NodeId>,
+    frames: Vec<usize>,
+    shared: BTreeMap<usize, NodeId>,
+}
+
+impl GraphBuilder {
+    /// A builder with an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The graph of everything that was evaluated.
+    pub fn finish(self) -> OpGraph {
+        self.graph
+    }
+}
+
+impl Trace for GraphBuilder {
+    fn enter(&mut self, _kind: OpKind, _label: Option<&'static str>) {
+        self.frames.push(self.stack.len());
+    }
+
+    fn exit(&mut self, node: &Exit<'_>) {
+        let start = self.frames.pop().expect("every exit to have an enter");
+        let data = OpData::from_operands(node.kind, node.param, &self.stack[start..]);
+        self.stack.truncate(start);
+
+        let id = self.graph.push(data, node.value);
+        if let Some(label) = node.label {
+            self.graph.set_label(id, label);
+        }
+
+        self.stack.push(id);
+    }
+
+    fn share(&mut self, key: usize) {
+        let top = *self.stack.last().expect("a shared op to have just exited");
+        let _ = self.shared.insert(key, top);
+    }
+
+    fn reuse(&mut self, key: usize, node: &Exit<'_>) {
+        // This is what makes graphs DAGs: every use of a shared op points at
+        // the same node.
+        let id = self.shared[&key];
+
+        if let (Some(label), None) = (node.label, self.graph[id].label()) {
+            self.graph.set_label(id, label);
+        }
+
+        self.stack.push(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{mux, Op, OpWrapper};
+    use super::super::imm::Imm;
+
+    #[test]
+    fn records_every_node() {
+        let a: OpWrapper<Imm<u8>> = 5u8.into();
+        let b: OpWrapper<Imm<u8>> = 7u8.into();
+        let c: OpWrapper<Imm<u8>> = 3u8.into();
+
+        let (v, g) = ((a + b) ^ !c).execute_with_graph(&());
+        assert_eq!(v, 240);
+        assert_eq!(g.len(), 6);
+
+        let root = &g[g.root().unwrap()];
+        assert_eq!(root.kind(), OpKind::Xor);
+        assert_eq!(root.value(), Bits::new(8, 240));
+
+        let ops = root.operands();
+        assert_eq!(g[ops[0]].kind(), OpKind::Addition);
+        assert_eq!(g[ops[0]].value(), Bits::new(8, 12));
+        assert_eq!(g[ops[1]].kind(), OpKind::Not);
+        assert_eq!(g[ops[1]].value(), Bits::new(8, 252));
+
+        let not_inp = g[ops[1]].operands()[0];
+        assert_eq!(g[not_inp].data(), &OpData::Immediate());
+        assert_eq!(g[not_inp].value(), Bits::new(8, 3));
+
+        // Operands always come first:
+        for id in g.ids() {
+            assert!(g[id].operands().iter().all(|o| *o < id));
+        }
+    }
+
+    #[test]
+    fn evaluate() {
+        let a: OpWrapper<Imm<u8>> = 5u8.into();
+        let b: OpWrapper<Imm<u8>> = 7u8.into();
+        let c: OpWrapper<Imm<u8>> = 3u8.into();
+
+        let (_, g) = ((a + b) ^ !c).execute_with_graph(&());
+        let recorded: Vec<Bits> = g.nodes().iter().map(|n| n.value()).collect();
+
+        assert_eq!(g.evaluate(|n, _| n.value()), recorded);
+    }
+
+    #[test]
+    fn muxes_record_both_inputs() {
+        let a: OpWrapper<Imm<u16>> = 500u16.into();
+        let b: OpWrapper<Imm<u16>> = 600u16.into();
+        let sel: OpWrapper<Imm<bool>> = false.into();
+
+        let (v, g) = mux(sel, a, b).execute_with_graph(&());
+        assert_eq!(v, 600);
+
+        let root = &g[g.root().unwrap()];
+        assert_eq!(root.kind(), OpKind::Mux);
+        assert_eq!(root.value(), Bits::new(16, 600));
+
+        let ops = root.operands();
+        assert_eq!(g[ops[0]].value(), Bits::new(1, 0));
+        assert_eq!(g[ops[1]].value(), Bits::new(16, 500));
+        assert_eq!(g[ops[2]].value(), Bits::new(16, 600));
+    }
+}
@@ -1,29 +1,36 @@
-use super::{Op, OpKind, OpWrapper};
+use super::{Exit, Op, OpKind, OpWrapper, Trace};
+use crate::bits::Value;
 
 use core::fmt::Display;
 
+/// A constant.
+///
+/// Any value that can be printed and recorded (see
+/// [`Value`](../bits/trait.Value.html)) can be turned into an immediate with
+/// `.into()`; i.e. `let a: OpWrapper<Imm<u8>> = 5u8.into();`.
 #[derive(Copy, Clone, Debug)]
 pub struct Imm<T: Display> {
     imm: T
 }
 
-impl<T: Display> From<T> for OpWrapper<Imm<T>> {
+impl<S, T: Clone + Display + Value> From<T> for OpWrapper<Imm<T>, S> {
     fn from(imm: T) -> Self {
         Imm { imm }.into()
     }
 }
 
-impl<T: Display> Op for Imm<T> {
+impl<S, T: Clone + Display + Value> Op<S> for Imm<T> {
     type Output = T;
     const OPERATION: OpKind = OpKind::Immediate;
 
-    fn execute(self) -> Self::Output {
-        self.imm
+    fn execute(&self, _state: &S) -> Self::Output {
+        self.imm.clone()
     }
 
-    fn execute_with_metadata(self) -> (String, Self::Output) {
-        (format!("{}", self.imm), self.imm)
-    }
+    fn execute_with_trace(&self, _state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::Immediate, None);
+        trace.exit(&Exit::new(OpKind::Immediate, &self.imm).with_display(&self.imm));
 
-    // fn execute_with_graph(self) ->
+        self.imm.clone()
+    }
 }
@@ -0,0 +1,166 @@
+use super::{Exit, Op, OpKind, OpWrapper, Param, Trace};
+use crate::bits::Value;
+use crate::state::{Memory, Registers};
+
+use core::fmt::Display;
+
+/// Reads a register out of the context.
+///
+/// Shows up in metadata as the register and the value that was read, i.e.
+/// `<r0=3>`.
+#[derive(Copy, Clone, Debug)]
+pub struct RegisterInput {
+    num: u8,
+}
+
+impl RegisterInput {
+    /// Reads register `num`.
+    pub fn new<S: Registers>(num: u8) -> OpWrapper<Self, S>
+    where
+        S::Word: Display + Value,
+    {
+        RegisterInput { num }.into()
+    }
+}
+
+impl<S: Registers> Op<S> for RegisterInput
+where
+    S::Word: Display + Value,
+{
+    type Output = S::Word;
+    const OPERATION: OpKind = OpKind::RegisterInput;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        state.read_reg(self.num)
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::RegisterInput, None);
+
+        let val = self.execute(state);
+        trace.exit(
+            &Exit::new(OpKind::RegisterInput, &val)
+                .with_display(&val)
+                .with_param(Param::Register(self.num)),
+        );
+
+        val
+    }
+}
+
+/// Reads memory out of the context at the address produced by an op.
+///
+/// Shows up in metadata as the address and the value that was read, i.e.
+/// `<mem[0x3000]=5>`.
+#[derive(Copy, Clone, Debug)]
+pub struct MemoryInput<A> {
+    addr: A,
+}
+
+impl<A> MemoryInput<A> {
+    /// Reads the memory location that `addr` points to.
+    pub fn new<S: Memory>(addr: OpWrapper<A, S>) -> OpWrapper<Self, S>
+    where
+        A: Op<S, Output = S::Addr>,
+        S::Word: Display + Value,
+    {
+        MemoryInput { addr: addr.unwrap() }.into()
+    }
+}
+
+impl<S: Memory, A: Op<S, Output = S::Addr>> Op<S> for MemoryInput<A>
+where
+    S::Word: Display + Value,
+{
+    type Output = S::Word;
+    const OPERATION: OpKind = OpKind::MemoryInput;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        state.read_mem(self.addr.execute(state))
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::MemoryInput, None);
+
+        let addr = self.addr.execute_with_trace(state, trace);
+        let val = state.read_mem(addr);
+
+        trace.exit(&Exit::new(OpKind::MemoryInput, &val).with_display(&val));
+        val
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use super::super::imm::Imm;
+
+    use core::cell::{Cell, RefCell};
+
+    struct State {
+        regs: Cell<[u16; 8]>,
+        mem: RefCell<[u16; 16]>,
+    }
+
+    impl Registers for State {
+        type Word = u16;
+
+        fn read_reg(&self, num: u8) -> u16 {
+            self.regs.get()[num as usize]
+        }
+
+        fn write_reg(&self, num: u8, word: u16) {
+            let mut regs = self.regs.get();
+            regs[num as usize] = word;
+            self.regs.set(regs);
+        }
+    }
+
+    impl Memory for State {
+        type Addr = u16;
+        type Word = u16;
+
+        fn read_mem(&self, addr: u16) -> u16 {
+            self.mem.borrow()[addr as usize]
+        }
+
+        fn write_mem(&self, addr: u16, word: u16) {
+            self.mem.borrow_mut()[addr as usize] = word;
+        }
+    }
+
+    fn state() -> State {
+        State {
+            regs: Cell::new([0; 8]),
+            mem: RefCell::new([0; 16]),
+        }
+    }
+
+    #[test]
+    fn register_input() {
+        let state = state();
+        state.write_reg(0, 3);
+
+        let a: OpWrapper<Imm<u16>, State> = 89.into();
+        let b: OpWrapper<Imm<u16>, State> = 90.into();
+        let r0 = RegisterInput::new(0);
+
+        let (m, v) = (a + b + r0).execute_with_metadata(&state);
+
+        assert_eq!(m, "((89 + 90) + <r0=3>)");
+        assert_eq!(v, 182);
+    }
+
+    #[test]
+    fn memory_input() {
+        let state = state();
+        state.write_reg(1, 4);
+        state.write_mem(4, 7);
+
+        let op: OpWrapper<_, State> = MemoryInput::new(RegisterInput::new(1));
+        let (m, v) = op.execute_with_metadata(&state);
+
+        assert_eq!(m, "<mem[<r1=4>]=7>");
+        assert_eq!(v, 7);
+    }
+}
@@ -1,37 +1,141 @@
 
-pub trait Op {
-    type Output;
+/// A node in an expression.
+///
+/// `S` is the context the expression is evaluated against (i.e. the machine
+/// state that ops like [`RegisterInput`](struct.RegisterInput.html) read from).
+/// Ops that don't read any state are implemented for every `S`.
+pub trait Op<S = ()> {
+    type Output: Value;
     const OPERATION: OpKind;
 
-    fn execute(self) -> Self::Output;
-    fn execute_with_metadata(self) -> (String, Self::Output);
+    fn execute(&self, state: &S) -> Self::Output;
 
-    // fn execute_with_graph(self) -> OpGraphNode;
+    /// Executes this op, telling `trace` about every op that's evaluated along
+    /// the way (this one and all of its operands).
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output;
+
+    /// Executes this op and returns its output along with a description of
+    /// the expression; i.e. `((89 + 90) + <r0=3>)`. See
+    /// [`Metadata`](struct.Metadata.html).
+    #[cfg(feature = "alloc")]
+    fn execute_with_metadata(&self, state: &S) -> (String, Self::Output)
+    where
+        Self: Sized,
+    {
+        render(self, state, Metadata)
+    }
+
+    /// Executes this op and returns its output along with a graph of every op
+    /// that was evaluated (and the value each produced).
+    #[cfg(feature = "alloc")]
+    fn execute_with_graph(&self, state: &S) -> (Self::Output, OpGraph)
+    where
+        Self: Sized,
+    {
+        let mut builder = GraphBuilder::new();
+        let out = self.execute_with_trace(state, &mut builder);
+
+        (out, builder.finish())
+    }
 }
 
+use crate::bits::Value;
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+#[cfg(feature = "alloc")]
+mod area;
+#[cfg(feature = "alloc")]
+mod dot;
+#[cfg(feature = "alloc")]
+mod equiv;
+#[cfg(feature = "alloc")]
+mod graph;
 mod op_types;
+#[cfg(feature = "alloc")]
+mod render;
+#[cfg(feature = "alloc")]
+mod shared;
+#[cfg(feature = "alloc")]
+mod simplify;
+#[cfg(feature = "alloc")]
+mod timing;
+mod trace;
 
-pub(crate) use op_types::OpWrapper;
-pub use op_types::LabeledOp;
+pub use cmp::{Cmp, Comparison, Eq, Ne, Sle, Slt, Ule, Ult};
+pub use concat::{concat, Concat};
+pub use imm::Imm;
+pub use op_types::{LabeledOp, OpWrapper, SameWidth};
+#[cfg(feature = "alloc")]
+pub use area::{AreaReport, AreaRow, CostTable, GateEquivalents};
+#[cfg(feature = "alloc")]
+pub use dot::to_dot;
+#[cfg(feature = "alloc")]
+pub use equiv::{Assignment, Counterexample, EquivError, Var, MAX_INPUT_WIDTH};
+#[cfg(feature = "alloc")]
+pub use graph::{GraphBuilder, NodeId, OpData, OpGraph, OpGraphNode};
+#[cfg(feature = "alloc")]
+pub(crate) use graph::{identical, Compared};
+#[cfg(feature = "alloc")]
+pub use render::{render, Infix, Metadata, Renderer, SExpr, Style};
+#[cfg(feature = "alloc")]
+pub use shared::{Run, Shared};
+#[cfg(feature = "alloc")]
+pub(crate) use shared::InRun;
+#[cfg(feature = "alloc")]
+pub use simplify::{Rule, Simplification};
+#[cfg(feature = "alloc")]
+pub use timing::{CriticalPath, Delay, Delays, PathStep};
+pub use trace::{Exit, Param, Trace};
+pub use mux::{mux, mux_n, Bit, Selector};
+pub use sext::sext;
+pub use slice::Slice;
+pub use zext::zext;
+pub use input::{MemoryInput, RegisterInput};
 
 mod add;
+mod and;
+mod cmp;
+mod concat;
 mod imm;
+mod input;
+mod mux;
+mod not;
+mod or;
+mod sext;
+mod slice;
+mod sub;
+mod xor;
+mod zext;
 
 
+/// The different kinds of op.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum OpKind {
     Immediate,
-    // RegisterInput,
+    RegisterInput,
+    MemoryInput,
     Addition,
-
-}
-
-// Until https://github.com/rust-lang/rfcs/pull/2593 happens, we're going to
-// have to keep the trait impls and this enum in sync manually.
-pub enum OpData {
-    Immediate()
-}
-
-pub struct OpGraphNode {
-    kind: OpKind,
-    data: OpData,
+    Subtraction,
+    And,
+    Or,
+    Xor,
+    Not,
+    Mux,
+    MuxN,
+    Zext,
+    Sext,
+    Eq,
+    Ne,
+    Ult,
+    Ule,
+    Slt,
+    Sle,
+    Slice,
+    Concat,
+    Register,
+    Input,
+    MemoryRead,
 }
@@ -0,0 +1,260 @@
+use super::{Exit, Op, OpKind, OpWrapper, SameWidth, Trace};
+use crate::wires::{BitCountType, FitsInU64, Wire};
+
+/// Values that can drive the select line of a mux.
+pub trait Selector {
+    /// The width of the select line, in bits.
+    const WIDTH: BitCountType;
+
+    /// The (0-indexed) input this value selects.
+    fn index(&self) -> usize;
+}
+
+/// Selectors that are exactly one bit wide (i.e. can drive a 2-to-1 mux).
+pub trait Bit: Selector {}
+
+impl Selector for bool {
+    const WIDTH: BitCountType = 1;
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl Bit for bool {}
+
+impl<const B: BitCountType, const S: usize> Selector for Wire<{ B }, { S }>
+where
+    Wire<{ B }, { S }>: FitsInU64,
+{
+    const WIDTH: BitCountType = B;
+
+    fn index(&self) -> usize {
+        u64::from(*self) as usize
+    }
+}
+
+impl Bit for Wire<{ 1 }, { 1 }> {}
+
+/// A 2-to-1 mux; `a` when the select line is set and `b` when it isn't.
+#[derive(Copy, Clone, Debug)]
+pub struct Mux<Sel, A, B> {
+    sel: Sel,
+    a: A,
+    b: B,
+}
+
+impl<S, Sel: Op<S>, A: Op<S>, B: Op<S>> Op<S> for Mux<Sel, A, B>
+where
+    Sel::Output: Bit,
+    A::Output: SameWidth<<B as Op<S>>::Output>,
+{
+    type Output = <A as Op<S>>::Output;
+    const OPERATION: OpKind = OpKind::Mux;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        if self.sel.execute(state).index() == 1 {
+            self.a.execute(state)
+        } else {
+            A::Output::same_width(self.b.execute(state))
+        }
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::Mux, None);
+
+        // We evaluate both inputs so that traces see the branch that wasn't
+        // taken too, unless the trace ignores everything (`OpWrapper::execute`
+        // runs expressions with one of those, to give shared ops a run).
+        let sel = self.sel.execute_with_trace(state, trace).index() == 1;
+        let out = if !trace.watching() {
+            if sel {
+                self.a.execute_with_trace(state, trace)
+            } else {
+                A::Output::same_width(self.b.execute_with_trace(state, trace))
+            }
+        } else {
+            let a = self.a.execute_with_trace(state, trace);
+            let b = A::Output::same_width(self.b.execute_with_trace(state, trace));
+
+            if sel { a } else { b }
+        };
+
+        trace.exit(&Exit::new(OpKind::Mux, &out));
+        out
+    }
+}
+
+/// Makes a 2-to-1 mux (`(sel ? a : b)`) out of a 1-bit select line and two
+/// inputs of the same type.
+pub fn mux<S, Sel: Op<S>, A: Op<S>, B: Op<S>>(
+    sel: OpWrapper<Sel, S>,
+    a: OpWrapper<A, S>,
+    b: OpWrapper<B, S>,
+) -> OpWrapper<Mux<Sel, A, B>, S>
+where
+    Sel::Output: Bit,
+    A::Output: SameWidth<<B as Op<S>>::Output>,
+{
+    Mux {
+        sel: sel.unwrap(),
+        a: a.unwrap(),
+        b: b.unwrap(),
+    }.into()
+}
+
+/// An N-to-1 mux; the select line picks one of the `N` inputs.
+#[derive(Copy, Clone, Debug)]
+pub struct MuxN<Sel, T, const N: usize> {
+    sel: Sel,
+    inputs: [T; N],
+}
+
+impl<S, Sel: Op<S>, T: Op<S>, const N: usize> Op<S> for MuxN<Sel, T, { N }>
+where
+    Sel::Output: Selector,
+{
+    type Output = <T as Op<S>>::Output;
+    const OPERATION: OpKind = OpKind::MuxN;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        let idx = self.sel.execute(state).index();
+
+        self.inputs.iter()
+            .nth(idx)
+            .expect("select line to be narrow enough for the mux")
+            .execute(state)
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::MuxN, None);
+
+        // As with `Mux`, every input gets evaluated if the trace is watching.
+        let idx = self.sel.execute_with_trace(state, trace).index();
+        let watching = trace.watching();
+        let mut out = None;
+
+        for (i, inp) in self.inputs.iter().enumerate() {
+            if i == idx {
+                out = Some(inp.execute_with_trace(state, trace));
+            } else if watching {
+                let _ = inp.execute_with_trace(state, trace);
+            }
+        }
+
+        let out = out.expect("select line to be narrow enough for the mux");
+
+        trace.exit(&Exit::new(OpKind::MuxN, &out));
+        out
+    }
+}
+
+/// Makes an N-to-1 mux out of a select line and an array of inputs.
+///
+/// The select line must be exactly `log2(N)` bits wide (and so `N` must be a
+/// power of two). Ideally this would be checked at compile time but that
+/// requires arithmetic on const generic parameters (which doesn't work yet) so
+/// for now this panics when the mux is constructed instead.
+pub fn mux_n<S, Sel: Op<S>, T: Op<S>, const N: usize>(
+    sel: OpWrapper<Sel, S>,
+    inputs: [OpWrapper<T, S>; N],
+) -> OpWrapper<MuxN<Sel, T, { N }>, S>
+where
+    Sel::Output: Selector,
+{
+    assert!(N.is_power_of_two(), "an N-way mux needs a power of two inputs; got {}", N);
+    assert_eq!(
+        <<Sel as Op<S>>::Output as Selector>::WIDTH,
+        N.trailing_zeros() as BitCountType,
+        "a {}-way mux needs a {} bit select line",
+        N,
+        N.trailing_zeros()
+    );
+
+    MuxN {
+        sel: sel.unwrap(),
+        inputs: inputs.map(OpWrapper::<T, S>::unwrap),
+    }.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::imm::Imm;
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn two_way() {
+        let a: OpWrapper<Imm<u8>> = 5u8.into();
+        let b: OpWrapper<Imm<u8>> = 7u8.into();
+        let sel: OpWrapper<Imm<bool>> = true.into();
+        let (m, v) = mux(sel, a, b).execute_with_metadata(&());
+
+        assert_eq!(m, "(true ? [5] : 7)");
+        assert_eq!(v, 5);
+
+        let a: OpWrapper<Imm<u8>> = 5u8.into();
+        let b: OpWrapper<Imm<u8>> = 7u8.into();
+        let sel: OpWrapper<Imm<bool>> = false.into();
+        let (m, v) = mux(sel, a, b).execute_with_metadata(&());
+
+        assert_eq!(m, "(false ? 5 : [7])");
+        assert_eq!(v, 7);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn n_way() {
+        let inputs: [OpWrapper<Imm<u8>>; 2] = [3u8.into(), 4u8.into()];
+        let sel: OpWrapper<Imm<bool>> = true.into();
+        let (m, v) = mux_n(sel, inputs).execute_with_metadata(&());
+
+        assert_eq!(m, "case(true){0: 3, 1: [4]}");
+        assert_eq!(v, 4);
+    }
+
+    #[test]
+    fn untaken_branches() {
+        use super::super::RegisterInput;
+        use crate::state::Registers;
+        use crate::w;
+        use core::cell::Cell;
+
+        /// Counts reads of each register.
+        #[derive(Default)]
+        struct Reads([Cell<usize>; 4]);
+
+        impl Registers for Reads {
+            type Word = u8;
+
+            fn read_reg(&self, num: u8) -> u8 {
+                let reads = &self.0[num as usize];
+                reads.set(reads.get() + 1);
+                num
+            }
+
+            fn write_reg(&self, _num: u8, _word: u8) {}
+        }
+
+        let r = |num| RegisterInput::new::<Reads>(num);
+        let sel: OpWrapper<Imm<bool>, Reads> = false.into();
+        let state = Reads::default();
+
+        assert_eq!(mux(sel, r(0), r(1)).execute(&state), 1);
+        assert_eq!(state.0[0].get(), 0);
+        assert_eq!(state.0[1].get(), 1);
+
+        let sel: OpWrapper<Imm<Wire<{ 2 }, { 1 }>>, Reads> = w!(2#2).into();
+        assert_eq!(mux_n(sel, [r(0), r(1), r(2), r(3)]).execute(&state), 2);
+        assert_eq!(state.0[2].get(), 1);
+        assert_eq!(state.0[3].get(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn n_way_with_a_narrow_select_line() {
+        let inputs: [OpWrapper<Imm<u8>>; 4] = [0u8.into(), 1u8.into(), 2u8.into(), 3u8.into()];
+        let sel: OpWrapper<Imm<bool>> = true.into();
+        let _ = mux_n(sel, inputs);
+    }
+}
@@ -0,0 +1,42 @@
+use super::{Exit, Op, OpKind, OpWrapper, Trace};
+
+use core::ops::Not as NotOp;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Not<T> {
+    inp: T,
+}
+
+impl<S, T: Op<S>> Op<S> for Not<T>
+where
+    T::Output: NotOp<Output = <T as Op<S>>::Output>
+{
+    type Output = <T as Op<S>>::Output;
+    const OPERATION: OpKind = OpKind::Not;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        !self.inp.execute(state)
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::Not, None);
+
+        let out = !self.inp.execute_with_trace(state, trace);
+
+        trace.exit(&Exit::new(OpKind::Not, &out));
+        out
+    }
+}
+
+impl<S, T: Op<S>> NotOp for OpWrapper<T, S>
+where
+    T::Output: NotOp<Output = <T as Op<S>>::Output>
+{
+    type Output = OpWrapper<Not<T>, S>;
+
+    fn not(self) -> Self::Output {
+        Not {
+            inp: self.unwrap(),
+        }.into()
+    }
+}
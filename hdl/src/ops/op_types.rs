@@ -1,33 +1,172 @@
-use super::{Op, OpKind};
+use super::{Op, OpKind, Trace};
+use super::trace::Labeled;
+#[cfg(feature = "alloc")]
+use super::shared::{InRun, Run};
 
-#[derive(Copy, Clone, Debug)]
-pub struct OpWrapper<T: Op>(T);
+use core::marker::PhantomData;
+
+/// Wraps an op so that we can implement operators (`+`, `&`, etc.) on it.
+///
+/// `S` is the type of the context (i.e. machine state) the op gets executed
+/// against. Most ops don't care what it is, but `S` still has to be part of the
+/// type so that operator impls know which `Op<S>` impl they're combining.
+#[derive(Debug)]
+pub struct OpWrapper<T: Op<S>, S = ()>(T, PhantomData<S>);
 
-impl<T: Op> OpWrapper<T> {
+impl<T: Op<S>, S> OpWrapper<T, S> {
     pub(crate) fn unwrap(self) -> T {
         self.0
     }
+
+    /// Gives this op a name.
+    ///
+    /// Labels don't change what an op does; they just show up in metadata
+    /// (`alu_out=(a + b)`) and graphs so that big expressions are easier to
+    /// pick apart.
+    pub fn label(self, label: &'static str) -> OpWrapper<LabeledOp<T>, S> {
+        LabeledOp(self.0, label).into()
+    }
+
+    /// Executes this op as part of `run`: [shared](struct.Shared.html) ops
+    /// that were already evaluated in `run` (i.e. by another expression) aren't
+    /// evaluated again.
+    ///
+    /// This is for evaluating several expressions that read the same values
+    /// (i.e. all the logic in a design, for one clock cycle) without repeating
+    /// the work they have in common.
+    #[cfg(feature = "alloc")]
+    pub fn execute_in(&self, state: &S, run: &mut Run) -> T::Output {
+        self.0.execute_with_trace(state, &mut InRun::new(&mut (), run))
+    }
 }
 
-impl<T: Op> Op for OpWrapper<T> {
-    type Output = <T as Op>::Output;
-    const OPERATION: OpKind = <T as Op>::OPERATION;
+impl<S, T: Op<S>> Op<S> for OpWrapper<T, S> {
+    type Output = <T as Op<S>>::Output;
+    const OPERATION: OpKind = <T as Op<S>>::OPERATION;
+
+    // `OpWrapper`s are only ever on the outside of an expression (operands are
+    // unwrapped) so this is where runs start. Shared ops get at the run through
+    // the trace, so with a heap everything is evaluated with a trace.
+    #[cfg(feature = "alloc")]
+    fn execute(&self, state: &S) -> Self::Output {
+        self.execute_in(state, &mut Run::new())
+    }
 
-    fn execute(self) -> Self::Output {
-        self.0.execute()
+    #[cfg(not(feature = "alloc"))]
+    fn execute(&self, state: &S) -> Self::Output {
+        self.0.execute(state)
     }
 
-    fn execute_with_metadata(self) -> (String, Self::Output) {
-        self.0.execute_with_metadata()
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        // An expression that's evaluated while another one is (i.e. by a
+        // trace) is part of the other expression's run.
+        #[cfg(feature = "alloc")]
+        {
+            if trace.run().is_none() {
+                return self.0.execute_with_trace(state, &mut InRun::new(trace, &mut Run::new()));
+            }
+        }
+
+        self.0.execute_with_trace(state, trace)
     }
 }
 
-impl<O: Op> From<O> for OpWrapper<O> {
+impl<S, O: Op<S>> From<O> for OpWrapper<O, S> {
     fn from(op: O) -> Self {
-        OpWrapper(op)
+        OpWrapper(op, PhantomData)
+    }
+}
+
+// `#[derive]` would require `S: Clone` and `S: Copy` which isn't what we want;
+// the context doesn't actually live in the wrapper.
+impl<T: Op<S> + Clone, S> Clone for OpWrapper<T, S> {
+    fn clone(&self) -> Self {
+        OpWrapper(self.0.clone(), PhantomData)
     }
 }
 
+impl<T: Op<S> + Copy, S> Copy for OpWrapper<T, S> {}
+
+/// Types that are the same width as `Rhs`.
+///
+/// This is the only bound binary operators on ops (`+`, `&`, ...) place on the
+/// type of their right hand side so mixing an 8 bit and a 16 bit wire gets a
+/// readable error message instead of a type mismatch deep inside an `Op` impl:
+///
+/// ```compile_fail,E0277
+/// #![feature(const_generics)]
+/// use hdl::ops::{Imm, OpWrapper};
+/// use hdl::wires::Wire;
+///
+/// let a: OpWrapper<Imm<Wire<{ 8 }, { 1 }>>> = Wire::new().into();
+/// let b: OpWrapper<Imm<Wire<{ 16 }, { 2 }>>> = Wire::new().into();
+///
+/// let _ = a + b; // `Wire<8, 1>` and `Wire<16, 2>` aren't the same width
+/// ```
+///
+/// Use `zext`/`sext` to widen the narrower side first.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` and `{Rhs}` aren't the same width",
+    label = "operands must be the same width; try extending the narrower one with `zext` or `sext`"
+)]
+pub trait SameWidth<Rhs> {
+    /// Turns the right hand side of an operator into the left hand side's
+    /// type.
+    fn same_width(rhs: Rhs) -> Self;
+}
+
+impl<T> SameWidth<T> for T {
+    fn same_width(rhs: T) -> T {
+        rhs
+    }
+}
 
+/// An op with a name; see [`OpWrapper::label`](struct.OpWrapper.html#method.label).
 #[derive(Copy, Clone, Debug)]
-pub struct LabeledOp<T: Op>(T, &'static str); // TODO!
+pub struct LabeledOp<T>(T, &'static str);
+
+impl<S, T: Op<S>> Op<S> for LabeledOp<T> {
+    type Output = <T as Op<S>>::Output;
+    const OPERATION: OpKind = <T as Op<S>>::OPERATION;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        self.0.execute(state)
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        // Labels don't get their own node; they're attached to the op they
+        // name.
+        self.0.execute_with_trace(state, &mut Labeled::new(trace, self.1))
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use super::super::imm::Imm;
+
+    #[test]
+    fn labels() {
+        let a: OpWrapper<Imm<u8>> = 2u8.into();
+        let b: OpWrapper<Imm<u8>> = 3u8.into();
+        let c: OpWrapper<Imm<u8>> = 4u8.into();
+
+        let op = (a + b).label("alu_out") & c.label("mask");
+        let (m, v) = op.execute_with_metadata(&());
+
+        assert_eq!(m, "(alu_out=(2 + 3) & mask=4)");
+        assert_eq!(v, 4);
+    }
+
+    #[test]
+    fn labels_in_graphs() {
+        let a: OpWrapper<Imm<u8>> = 2u8.into();
+        let b: OpWrapper<Imm<u8>> = 3u8.into();
+        let (v, g) = (a + b).label("alu_out").execute_with_graph(&());
+
+        assert_eq!(v, 5);
+        assert_eq!(g.len(), 3);
+        assert_eq!(g[g.root().unwrap()].label(), Some("alu_out"));
+        assert_eq!(g.nodes()[0].label(), None);
+    }
+}
@@ -0,0 +1,51 @@
+use super::{Exit, Op, OpKind, OpWrapper, SameWidth, Trace};
+
+use core::ops::BitOr as BitOrOp;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Or<L, R> {
+    lhs: L,
+    rhs: R,
+}
+
+impl<S, L: Op<S>, R: Op<S>> Op<S> for Or<L, R>
+where
+    L::Output: SameWidth<<R as Op<S>>::Output>,
+    L::Output: BitOrOp<Output = <L as Op<S>>::Output>,
+{
+    type Output = <L as Op<S>>::Output;
+    const OPERATION: OpKind = OpKind::Or;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        let lhs = self.lhs.execute(state);
+        let rhs = L::Output::same_width(self.rhs.execute(state));
+
+        lhs | rhs
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::Or, None);
+
+        let lhs = self.lhs.execute_with_trace(state, trace);
+        let rhs = L::Output::same_width(self.rhs.execute_with_trace(state, trace));
+        let out = lhs | rhs;
+
+        trace.exit(&Exit::new(OpKind::Or, &out));
+        out
+    }
+}
+
+impl<S, L: Op<S>, R: Op<S>> BitOrOp<OpWrapper<R, S>> for OpWrapper<L, S>
+where
+    L::Output: SameWidth<<R as Op<S>>::Output>,
+    L::Output: BitOrOp<Output = <L as Op<S>>::Output>,
+{
+    type Output = OpWrapper<Or<L, R>, S>;
+
+    fn bitor(self, rhs: OpWrapper<R, S>) -> Self::Output {
+        Or {
+            lhs: self.unwrap(),
+            rhs: rhs.unwrap(),
+        }.into()
+    }
+}
@@ -0,0 +1,301 @@
+//! Traces that render expressions as strings.
+//!
+//! A [`Renderer`] keeps a stack of the rendered forms of the ops that have
+//! exited but haven't been used yet; when an op exits, its operands are the
+//! entries pushed since it entered. How each op is actually printed is up to
+//! a [`Style`]:
+//!
+//!   - [`Metadata`]: what `execute_with_metadata` produces; infix, with the
+//!     values of inputs and the taken branches of muxes marked:
+//!     `alu_out=(<r0=3> + 4)`, `(true ? [5] : 7)`
+//!   - [`SExpr`]: s-expressions: `(label alu_out (+ r0 4))`
+//!   - [`Infix`]: fully parenthesized infix, without any values:
+//!     `(r0 + 4)`
+//!
+//! [`Renderer`]: struct.Renderer.html
+//! [`Style`]: trait.Style.html
+//! [`Metadata`]: struct.Metadata.html
+//! [`SExpr`]: struct.SExpr.html
+//! [`Infix`]: struct.Infix.html
+
+use super::{Exit, Op, OpKind, Param, Trace};
+use crate::bits::Bits;
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// How a [`Renderer`](struct.Renderer.html) prints each op.
+pub trait Style {
+    /// Renders an op, given the rendered forms (and values) of its operands.
+    fn render(&self, node: &Exit<'_>, operands: &[(String, Bits)]) -> String;
+}
+
+/// A trace that renders the evaluated expression as a string.
+#[derive(Debug)]
+pub struct Renderer<St: Style> {
+    style: St,
+    stack: Vec<(String, Bits)>,
+    frames: Vec<usize>,
+    shared: BTreeMap<usize, (String, Bits)>,
+}
+
+impl<St: Style> Renderer<St> {
+    /// A renderer that prints ops in the given style.
+    pub fn new(style: St) -> Self {
+        Renderer {
+            style,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            shared: BTreeMap::new(),
+        }
+    }
+
+    /// The rendered expression (empty if nothing was evaluated).
+    pub fn finish(mut self) -> String {
+        self.stack.pop().map(|(s, _)| s).unwrap_or_default()
+    }
+}
+
+impl<St: Style> Trace for Renderer<St> {
+    fn enter(&mut self, _kind: OpKind, _label: Option<&'static str>) {
+        self.frames.push(self.stack.len());
+    }
+
+    fn exit(&mut self, node: &Exit<'_>) {
+        let start = self.frames.pop().expect("every exit to have an enter");
+        let rendered = self.style.render(node, &self.stack[start..]);
+
+        self.stack.truncate(start);
+        self.stack.push((rendered, node.value));
+    }
+
+    fn share(&mut self, key: usize) {
+        let top = self.stack.last().expect("a shared op to have just exited").clone();
+        let _ = self.shared.insert(key, top);
+    }
+
+    fn reuse(&mut self, key: usize, _node: &Exit<'_>) {
+        // Shared ops are rendered in full at every use.
+        let prev = self.shared[&key].clone();
+        self.stack.push(prev);
+    }
+}
+
+/// Executes `op` and renders it in the given style.
+pub fn render<S, O: Op<S>, St: Style>(op: &O, state: &S, style: St) -> (String, O::Output) {
+    let mut renderer = Renderer::new(style);
+    let out = op.execute_with_trace(state, &mut renderer);
+
+    (renderer.finish(), out)
+}
+
+/// The infix symbol for binary ops.
+fn symbol(kind: OpKind) -> Option<&'static str> {
+    use OpKind::*;
+
+    match kind {
+        Addition => Some("+"),
+        Subtraction => Some("-"),
+        And => Some("&"),
+        Or => Some("|"),
+        Xor => Some("^"),
+        Eq => Some("=="),
+        Ne => Some("!="),
+        Ult => Some("<"),
+        Ule => Some("<="),
+        Slt => Some("<s"),
+        Sle => Some("<=s"),
+        _ => None,
+    }
+}
+
+/// The register a `RegisterInput` read.
+fn register(node: &Exit<'_>) -> u8 {
+    match node.param {
+        Param::Register(num) => num,
+        _ => 0,
+    }
+}
+
+/// The name of the state element, memory, or port an op reads.
+fn name(node: &Exit<'_>) -> &'static str {
+    match node.param {
+        Param::Name(name) => name,
+        _ => "?",
+    }
+}
+
+/// The `[hi:lo]` of a `Slice`.
+fn range(node: &Exit<'_>) -> String {
+    match node.param {
+        Param::Range { hi, lo } => format!("[{}:{}]", hi, lo),
+        _ => String::new(),
+    }
+}
+
+/// The value of an op as its own type would print it (if it told us how).
+fn shown(node: &Exit<'_>) -> String {
+    match node.display {
+        Some(d) => d.to_string(),
+        None => node.value.to_string(),
+    }
+}
+
+/// The string form of `execute_with_metadata`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Metadata;
+
+impl Style for Metadata {
+    fn render(&self, node: &Exit<'_>, ops: &[(String, Bits)]) -> String {
+        use OpKind::*;
+
+        let s = match node.kind {
+            Immediate => shown(node),
+            RegisterInput => format!("<r{}={}>", register(node), shown(node)),
+            MemoryInput => format!("<mem[{}]={}>", ops[0].0, shown(node)),
+            Register | Input => format!("<{}={}>", name(node), shown(node)),
+            MemoryRead => format!("<{}[{}]={}>", name(node), ops[0].0, shown(node)),
+            Not => format!("!{}", ops[0].0),
+            Mux => {
+                // The branch that was taken is marked with square brackets.
+                if ops[0].1.val() == 1 {
+                    format!("({} ? [{}] : {})", ops[0].0, ops[1].0, ops[2].0)
+                } else {
+                    format!("({} ? {} : [{}])", ops[0].0, ops[1].0, ops[2].0)
+                }
+            }
+            MuxN => {
+                // As with `Mux`, the selected input is marked.
+                let idx = ops[0].1.val() as usize;
+                let inputs: Vec<String> = ops[1..]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (m, _))| {
+                        if i == idx { format!("{}: [{}]", i, m) } else { format!("{}: {}", i, m) }
+                    })
+                    .collect();
+
+                format!("case({}){{{}}}", ops[0].0, inputs.join(", "))
+            }
+            Zext => format!("zext{}({})", node.value.width(), ops[0].0),
+            Sext => format!("sext{}({})", node.value.width(), ops[0].0),
+            Slice => format!("{}{}", ops[0].0, range(node)),
+            Concat => format!("{{{}, {}}}", ops[0].0, ops[1].0),
+            kind => format!("({} {} {})", ops[0].0, symbol(kind).unwrap(), ops[1].0),
+        };
+
+        match node.label {
+            Some(l) => format!("{}={}", l, s),
+            None => s,
+        }
+    }
+}
+
+/// S-expressions: `(+ (not a) b)`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SExpr;
+
+impl Style for SExpr {
+    fn render(&self, node: &Exit<'_>, ops: &[(String, Bits)]) -> String {
+        use OpKind::*;
+
+        let head = match node.kind {
+            Immediate => return label_sexpr(node, shown(node)),
+            RegisterInput => return label_sexpr(node, format!("r{}", register(node))),
+            Register | Input => return label_sexpr(node, name(node).to_string()),
+            MemoryInput => "mem".to_string(),
+            MemoryRead => name(node).to_string(),
+            Not => "not".to_string(),
+            Mux => "mux".to_string(),
+            MuxN => "case".to_string(),
+            Zext => format!("zext{}", node.value.width()),
+            Sext => format!("sext{}", node.value.width()),
+            Slice => match node.param {
+                Param::Range { hi, lo } => format!("slice {} {}", hi, lo),
+                _ => "slice".to_string(),
+            },
+            Concat => "concat".to_string(),
+            kind => symbol(kind).unwrap().to_string(),
+        };
+
+        let mut s = format!("({}", head);
+        for (o, _) in ops {
+            s.push(' ');
+            s.push_str(o);
+        }
+        s.push(')');
+
+        label_sexpr(node, s)
+    }
+}
+
+fn label_sexpr(node: &Exit<'_>, s: String) -> String {
+    match node.label {
+        Some(l) => format!("(label {} {})", l, s),
+        None => s,
+    }
+}
+
+/// Fully parenthesized infix, without values or labels: `((!a) + b)`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Infix;
+
+impl Style for Infix {
+    fn render(&self, node: &Exit<'_>, ops: &[(String, Bits)]) -> String {
+        use OpKind::*;
+
+        match node.kind {
+            Immediate => shown(node),
+            RegisterInput => format!("r{}", register(node)),
+            MemoryInput => format!("mem[{}]", ops[0].0),
+            Register | Input => name(node).to_string(),
+            MemoryRead => format!("{}[{}]", name(node), ops[0].0),
+            Not => format!("(!{})", ops[0].0),
+            Mux => format!("({} ? {} : {})", ops[0].0, ops[1].0, ops[2].0),
+            MuxN => {
+                let inputs: Vec<&str> = ops[1..].iter().map(|(o, _)| o.as_str()).collect();
+                format!("case({}){{{}}}", ops[0].0, inputs.join(", "))
+            }
+            Zext => format!("zext{}({})", node.value.width(), ops[0].0),
+            Sext => format!("sext{}({})", node.value.width(), ops[0].0),
+            Slice => format!("{}{}", ops[0].0, range(node)),
+            Concat => format!("{{{}, {}}}", ops[0].0, ops[1].0),
+            kind => format!("({} {} {})", ops[0].0, symbol(kind).unwrap(), ops[1].0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{mux, OpWrapper};
+    use super::super::imm::Imm;
+
+    fn expr() -> OpWrapper<impl Op<Output = u8>> {
+        let a: OpWrapper<Imm<u8>> = 5u8.into();
+        let b: OpWrapper<Imm<u8>> = 3u8.into();
+        let c: OpWrapper<Imm<u8>> = 9u8.into();
+        let sel: OpWrapper<Imm<bool>> = false.into();
+
+        mux(sel, c, (!a + b).label("sum"))
+    }
+
+    #[test]
+    fn metadata() {
+        assert_eq!(
+            render(&expr(), &(), Metadata),
+            (String::from("(false ? 9 : [sum=(!5 + 3)])"), 253)
+        );
+    }
+
+    #[test]
+    fn sexpr() {
+        assert_eq!(render(&expr(), &(), SExpr).0, "(mux false 9 (label sum (+ (not 5) 3)))");
+    }
+
+    #[test]
+    fn infix() {
+        assert_eq!(render(&expr(), &(), Infix).0, "(false ? 9 : ((!5) + 3))");
+    }
+}
@@ -0,0 +1,105 @@
+use super::{Exit, Op, OpKind, OpWrapper, Trace};
+use crate::bits::Value;
+use crate::wires::{BitCountType, Extend, Width, WireOfWidth};
+
+use core::marker::PhantomData;
+
+/// Sign extends the output of an op to the wire type `W`.
+#[derive(Copy, Clone, Debug)]
+pub struct Sext<T, W> {
+    inp: T,
+    _out: PhantomData<W>,
+}
+
+impl<S, T: Op<S>, W> Op<S> for Sext<T, W>
+where
+    T::Output: Extend<W>,
+    W: Value,
+{
+    type Output = W;
+    const OPERATION: OpKind = OpKind::Sext;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        self.inp.execute(state).sext()
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::Sext, None);
+
+        let out = self.inp.execute_with_trace(state, trace).sext();
+
+        trace.exit(&Exit::new(OpKind::Sext, &out));
+        out
+    }
+}
+
+impl<S, T: Op<S>> OpWrapper<T, S> {
+    /// Sign extends this op's output to `N` bits.
+    pub fn sext<const N: BitCountType>(self) -> OpWrapper<Sext<T, <Width<{ N }> as WireOfWidth>::Wire>, S>
+    where
+        Width<{ N }>: WireOfWidth,
+        T::Output: Extend<<Width<{ N }> as WireOfWidth>::Wire>,
+    {
+        let (from, to) = (
+            <T::Output as Extend<<Width<{ N }> as WireOfWidth>::Wire>>::FROM,
+            <T::Output as Extend<<Width<{ N }> as WireOfWidth>::Wire>>::TO,
+        );
+        assert!(to >= from, "can't sign extend a {} bit wire to {} bits", from, to);
+
+        Sext {
+            inp: self.unwrap(),
+            _out: PhantomData,
+        }.into()
+    }
+}
+
+/// Sign extends the output of `op` to `N` bits: `sext::<16, _>(a)`.
+///
+/// The second parameter is the type of the context; Rust won't let us specify
+/// just the first generic parameter of a function so it has to be spelled out
+/// (as `_`). `a.sext::<16>()` doesn't have this problem.
+pub fn sext<const N: BitCountType, S>(
+    op: OpWrapper<impl Op<S, Output = impl Extend<<Width<{ N }> as WireOfWidth>::Wire> + Value>, S>,
+) -> OpWrapper<impl Op<S, Output = <Width<{ N }> as WireOfWidth>::Wire>, S>
+where
+    Width<{ N }>: WireOfWidth,
+{
+    op.sext::<{ N }>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::imm::Imm;
+    use crate::w;
+    use crate::wires::Wire;
+
+    type W8 = Wire<{ 8 }, { 1 }>;
+
+    #[test]
+    fn negative() {
+        // -10 stays -10:
+        let a: OpWrapper<Imm<W8>> = w!(8#0xF6).into();
+        assert_eq!(u16::from(a.sext::<16>().execute(&())), 0xFFF6);
+
+        let a: OpWrapper<Imm<W8>> = w!(8#0x76).into();
+        assert_eq!(u16::from(sext::<16, _>(a).execute(&())), 0x0076);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn metadata() {
+        let a: OpWrapper<Imm<W8>> = w!(8#0x80).into();
+        let (m, v) = a.sext::<16>().execute_with_metadata(&());
+
+        assert_eq!(m, "sext16(8'h80)");
+        assert_eq!(u16::from(v), 0xFF80);
+    }
+
+    #[test]
+    #[should_panic]
+    fn narrowing() {
+        let a: OpWrapper<Imm<W8>> = w!(8#0x80).into();
+        let _ = a.sext::<4>();
+    }
+}
@@ -0,0 +1,257 @@
+//! Ops that feed more than one consumer.
+//!
+//! Ops own their operands so without any help an expression is a tree: using a
+//! signal twice means building (and evaluating) it twice. Wrapping an op in a
+//! [`Shared`] handle (with [`OpWrapper::share`]) gives a cheaply cloneable op
+//! that is evaluated at most once per run, no matter how many places use it.
+//!
+//! A [`Run`] is one evaluation: one call to one of the `execute` methods on an
+//! `OpWrapper` (i.e. a top level expression), or every call to
+//! [`OpWrapper::execute_in`] that's given the same `Run`. Shared ops save their
+//! outputs in the run, which reaches them through the [`Trace`] the expression
+//! is evaluated with ([`Trace::run`]).
+//!
+//! [`Shared`]: struct.Shared.html
+//! [`OpWrapper::share`]: struct.OpWrapper.html#method.share
+//! [`Run`]: struct.Run.html
+//! [`OpWrapper::execute_in`]: struct.OpWrapper.html#method.execute_in
+//! [`Trace`]: trait.Trace.html
+//! [`Trace::run`]: trait.Trace.html#method.run
+
+use super::{Exit, Op, OpKind, OpWrapper, Trace};
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use core::any::Any;
+use core::fmt::{self, Debug};
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Hands out the keys that identify shared ops in runs and traces.
+///
+/// The address of a shared op's `Rc` isn't enough: a run can outlive the
+/// expressions it was used with, and a new op may be allocated where a dropped
+/// one was (and then pick up its saved output).
+static NEXT_KEY: AtomicUsize = AtomicUsize::new(0);
+
+/// The outputs of the shared ops that have been evaluated so far in one
+/// evaluation; see the [module docs](index.html).
+///
+/// A run should only span evaluations that see the same values (i.e. the
+/// same state and the same clock cycle); shared ops don't check whether what
+/// they read has changed.
+#[derive(Debug, Default)]
+pub struct Run {
+    outputs: BTreeMap<usize, Box<dyn Any>>,
+}
+
+impl Run {
+    /// A run in which nothing has been evaluated yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The output of the shared op with `key`, if it was already evaluated.
+    fn saved<T: Clone + 'static>(&self, key: usize) -> Option<T> {
+        self.outputs.get(&key).and_then(|o| o.downcast_ref::<T>()).cloned()
+    }
+
+    fn save<T: 'static>(&mut self, key: usize, out: T) {
+        let _ = self.outputs.insert(key, Box::new(out));
+    }
+}
+
+/// Passes events along to another trace and gives shared ops a run to save
+/// their outputs in.
+pub(crate) struct InRun<'a> {
+    trace: &'a mut dyn Trace,
+    run: &'a mut Run,
+}
+
+impl<'a> InRun<'a> {
+    pub(crate) fn new(trace: &'a mut dyn Trace, run: &'a mut Run) -> Self {
+        InRun { trace, run }
+    }
+}
+
+impl Debug for InRun<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InRun").field("run", &self.run).finish()
+    }
+}
+
+impl Trace for InRun<'_> {
+    fn enter(&mut self, kind: OpKind, label: Option<&'static str>) {
+        self.trace.enter(kind, label)
+    }
+
+    fn exit(&mut self, node: &Exit<'_>) {
+        self.trace.exit(node)
+    }
+
+    fn share(&mut self, key: usize) {
+        self.trace.share(key)
+    }
+
+    fn reuse(&mut self, key: usize, node: &Exit<'_>) {
+        self.trace.reuse(key, node)
+    }
+
+    fn run(&mut self) -> Option<&mut Run> {
+        Some(self.run)
+    }
+
+    fn watching(&self) -> bool {
+        self.trace.watching()
+    }
+}
+
+struct Inner<T> {
+    op: T,
+    key: usize,
+}
+
+/// An op that can be used in more than one place; see the
+/// [module docs](index.html).
+pub struct Shared<T: Op<S>, S> {
+    inner: Rc<Inner<T>>,
+    _state: PhantomData<S>,
+}
+
+impl<S, T: Op<S>> Op<S> for Shared<T, S>
+where
+    T::Output: Clone + 'static,
+{
+    type Output = <T as Op<S>>::Output;
+    const OPERATION: OpKind = <T as Op<S>>::OPERATION;
+
+    /// Outside of a run there's nowhere to save our output so this always
+    /// evaluates the op; `OpWrapper`s evaluate expressions in a run.
+    fn execute(&self, state: &S) -> Self::Output {
+        self.inner.op.execute(state)
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        let key = self.inner.key;
+
+        if let Some(out) = trace.run().and_then(|r| r.saved::<T::Output>(key)) {
+            trace.reuse(key, &Exit::new(Self::OPERATION, &out));
+            return out;
+        }
+
+        let out = self.inner.op.execute_with_trace(state, trace);
+        trace.share(key);
+        if let Some(run) = trace.run() {
+            run.save(key, out.clone());
+        }
+
+        out
+    }
+}
+
+// As with `OpWrapper`, `#[derive]` would add bounds we don't want: cloning a
+// `Shared` just clones the handle.
+impl<T: Op<S>, S> Clone for Shared<T, S> {
+    fn clone(&self) -> Self {
+        Shared { inner: self.inner.clone(), _state: PhantomData }
+    }
+}
+
+impl<T: Op<S>, S> Debug for Shared<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shared").field("key", &self.inner.key).finish()
+    }
+}
+
+impl<S, T: Op<S>> OpWrapper<T, S>
+where
+    T::Output: Clone + 'static,
+{
+    /// Makes this op shareable: the returned op can be cloned and used any
+    /// number of times but is only evaluated once per run.
+    pub fn share(self) -> OpWrapper<Shared<T, S>, S> {
+        Shared {
+            inner: Rc::new(Inner { op: self.unwrap(), key: NEXT_KEY.fetch_add(1, Ordering::Relaxed) }),
+            _state: PhantomData,
+        }.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::RegisterInput;
+    use super::*;
+    use crate::state::Registers;
+
+    use core::cell::Cell;
+
+    /// A register file with one register that counts how many times it's read.
+    struct Reg {
+        val: Cell<u8>,
+        reads: Cell<usize>,
+    }
+
+    impl Registers for Reg {
+        type Word = u8;
+
+        fn read_reg(&self, _num: u8) -> u8 {
+            self.reads.set(self.reads.get() + 1);
+            self.val.get()
+        }
+
+        fn write_reg(&self, _num: u8, word: u8) {
+            self.val.set(word)
+        }
+    }
+
+    #[test]
+    fn evaluated_once_per_run() {
+        let state = Reg { val: Cell::new(3), reads: Cell::new(0) };
+        let r = RegisterInput::new::<Reg>(0).share();
+        let op = (r.clone() + r.clone()) ^ r;
+
+        assert_eq!(op.execute(&state), 5);
+        assert_eq!(state.reads.get(), 1);
+
+        state.write_reg(0, 4);
+        assert_eq!(op.execute(&state), 12);
+        assert_eq!(state.reads.get(), 2);
+
+        let (m, v) = op.execute_with_metadata(&state);
+        assert_eq!(m, "((<r0=4> + <r0=4>) ^ <r0=4>)");
+        assert_eq!(v, 12);
+        assert_eq!(state.reads.get(), 3);
+    }
+
+    #[test]
+    fn graphs_are_dags() {
+        let state = Reg { val: Cell::new(3), reads: Cell::new(0) };
+        let r = RegisterInput::new::<Reg>(0).share();
+        let (v, g) = ((r.clone() + r.clone()) ^ r).execute_with_graph(&state);
+
+        assert_eq!(v, 5);
+        assert_eq!(g.len(), 3);
+
+        let add = g.nodes()[1].operands();
+        assert_eq!(add[0], add[1]);
+        assert_eq!(g[g.root().unwrap()].operands(), [add[0], g.ids().nth(1).unwrap()]);
+    }
+
+    #[test]
+    fn runs_span_expressions() {
+        let state = Reg { val: Cell::new(3), reads: Cell::new(0) };
+        let r = RegisterInput::new::<Reg>(0).share();
+        let (sum, diff) = (r.clone() + r.clone(), r.clone() - r);
+
+        let mut run = Run::new();
+        assert_eq!(sum.execute_in(&state, &mut run), 6);
+        assert_eq!(diff.execute_in(&state, &mut run), 0);
+        assert_eq!(state.reads.get(), 1);
+
+        // A new run starts from scratch (and doesn't disturb the other one).
+        assert_eq!(sum.execute(&state), 6);
+        assert_eq!(diff.execute_in(&state, &mut run), 0);
+        assert_eq!(state.reads.get(), 2);
+    }
+}
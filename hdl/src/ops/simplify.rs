@@ -0,0 +1,376 @@
+//! Constant folding and algebraic simplification of evaluated expressions.
+//!
+//! [`OpGraph::simplify`] produces a smaller graph that evaluates identically
+//! (for any input values) along with a report of every rewrite it made. The
+//! report is the interesting part for students: it says which parts of an
+//! expression were redundant, and why.
+//!
+//! The graph an expression is recorded as (with [`Op::execute_with_graph`])
+//! doesn't depend on the values that were used to record it, so simplifying it
+//! simplifies the expression: the simplified graph can be run against fresh
+//! inputs with [`OpGraph::evaluate`]. The node values stored in the simplified
+//! graph are the ones from the recording.
+//!
+//! Simplification works on the nodes in order so rewrites compose: operands
+//! are simplified before the ops that use them.
+//!
+//! [`OpGraph::simplify`]: struct.OpGraph.html#method.simplify
+//! [`Op::execute_with_graph`]: trait.Op.html#method.execute_with_graph
+//! [`OpGraph::evaluate`]: struct.OpGraph.html#method.evaluate
+
+use super::{identical, Compared, NodeId, OpData, OpGraph, OpGraphNode, OpKind};
+use crate::bits::Bits;
+
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+
+/// A simplification rule.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// An op whose operands are all immediates is replaced by its value.
+    ConstantFold,
+    /// `x + 0`, `0 + x` and `x - 0` are `x`.
+    AddZero,
+    /// `x & 0` is `0`.
+    AndZero,
+    /// `x & !0` is `x`.
+    AndOnes,
+    /// `x | 0` is `x`.
+    OrZero,
+    /// `x | !0` is `!0`.
+    OrOnes,
+    /// `x ^ x` is `0`.
+    XorSelf,
+    /// `x ^ 0` is `x`.
+    XorZero,
+    /// `!!x` is `x`.
+    DoubleNot,
+    /// `zext(zext(x))` is `zext(x)` (and the same for `sext`).
+    ExtendOfExtend,
+    /// Extending to the width a value already has does nothing.
+    NoOpExtend,
+    /// A mux with an immediate select line always picks the same input.
+    ConstantSelect,
+    /// Comparing something to itself always gives the same answer.
+    CompareSelf,
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Rule::*;
+
+        f.write_str(match self {
+            ConstantFold => "only depends on immediates so it is a constant",
+            AddZero => "adding or subtracting 0 does nothing",
+            AndZero => "x & 0 is always 0",
+            AndOnes => "x & !0 is always x",
+            OrZero => "x | 0 is always x",
+            OrOnes => "x | !0 is always !0",
+            XorSelf => "x ^ x is always 0",
+            XorZero => "x ^ 0 is always x",
+            DoubleNot => "!!x is x",
+            ExtendOfExtend => "extending an extended value is the same as extending once",
+            NoOpExtend => "extending to the same width does nothing",
+            ConstantSelect => "the select line is an immediate so the same input is always picked",
+            CompareSelf => "comparing x to x always gives the same answer",
+        })
+    }
+}
+
+/// A rewrite that [`OpGraph::simplify`](struct.OpGraph.html#method.simplify)
+/// made.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Simplification {
+    /// The node that was rewritten (in the original graph).
+    pub node: NodeId,
+    /// Why.
+    pub rule: Rule,
+    /// If the node was labeled and was replaced by another node that has a
+    /// different label, the node's label and the label it goes by now (nodes
+    /// only have one label so the first one is gone from the simplified
+    /// graph).
+    pub relabeled: Option<(&'static str, &'static str)>,
+}
+
+impl Display for Simplification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "n{}: {}", self.node.idx(), self.rule)?;
+
+        match self.relabeled {
+            Some((old, new)) => write!(f, " (`{}` is now `{}`)", old, new),
+            None => Ok(()),
+        }
+    }
+}
+
+/// What to replace a node with.
+enum Rewrite {
+    /// An existing node.
+    Use(NodeId),
+    /// An immediate.
+    Const(Bits),
+    /// A new op.
+    Op(OpData),
+}
+
+/// Finds a rule that applies to `node` (whose operands, in `g`, are `ops`).
+fn rewrite(
+    g: &OpGraph,
+    memo: &mut Compared,
+    node: &OpGraphNode,
+    ops: &[NodeId],
+) -> Option<(Rewrite, Rule)> {
+    use OpKind::*;
+    use Rewrite::*;
+    use Rule::*;
+
+    let imm = |id: NodeId| g[id].kind() == Immediate;
+    let zero = |id: NodeId| imm(id) && g[id].value().val() == 0;
+    let ones = |id: NodeId| imm(id) && g[id].value().is_ones();
+    let width = node.value().width();
+    let mut same = |l: NodeId, r: NodeId| identical(g, l, g, r, memo);
+
+    match node.kind() {
+        Immediate | RegisterInput | MemoryInput | Register | Input | MemoryRead => return None,
+        _ if ops.iter().all(|o| imm(*o)) => return Some((Const(node.value()), ConstantFold)),
+        _ => {}
+    }
+
+    Some(match (node.kind(), ops) {
+        (Addition, &[l, r]) | (Subtraction, &[l, r]) if zero(r) => (Use(l), AddZero),
+        (Addition, &[l, r]) if zero(l) => (Use(r), AddZero),
+
+        (And, &[l, r]) if zero(l) || zero(r) => (Const(Bits::new(width, 0)), AndZero),
+        (And, &[l, r]) if ones(r) => (Use(l), AndOnes),
+        (And, &[l, r]) if ones(l) => (Use(r), AndOnes),
+
+        (Or, &[l, r]) if ones(l) || ones(r) => (Const(Bits::new(width, Bits::mask(width))), OrOnes),
+        (Or, &[l, r]) if zero(r) => (Use(l), OrZero),
+        (Or, &[l, r]) if zero(l) => (Use(r), OrZero),
+
+        (Xor, &[l, r]) if same(l, r) => (Const(Bits::new(width, 0)), XorSelf),
+        (Xor, &[l, r]) if zero(r) => (Use(l), XorZero),
+        (Xor, &[l, r]) if zero(l) => (Use(r), XorZero),
+
+        (Not, &[i]) if g[i].kind() == Not => (Use(g[i].operands()[0]), DoubleNot),
+
+        (Zext, &[i]) | (Sext, &[i]) if g[i].value().width() == width => (Use(i), NoOpExtend),
+        (Zext, &[i]) if g[i].kind() == Zext => {
+            (Op(OpData::Zext { inp: g[i].operands()[0] }), ExtendOfExtend)
+        }
+        (Sext, &[i]) if g[i].kind() == Sext => {
+            (Op(OpData::Sext { inp: g[i].operands()[0] }), ExtendOfExtend)
+        }
+
+        (Eq, &[l, r]) | (Ule, &[l, r]) | (Sle, &[l, r]) if same(l, r) => {
+            (Const(Bits::new(1, 1)), CompareSelf)
+        }
+        (Ne, &[l, r]) | (Ult, &[l, r]) | (Slt, &[l, r]) if same(l, r) => {
+            (Const(Bits::new(1, 0)), CompareSelf)
+        }
+
+        (Mux, &[s, a, b]) if imm(s) => {
+            (Use(if g[s].value().val() == 1 { a } else { b }), ConstantSelect)
+        }
+        (MuxN, _) if imm(ops[0]) => (Use(ops[1 + g[ops[0]].value().val() as usize]), ConstantSelect),
+
+        _ => return None,
+    })
+}
+
+impl OpGraph {
+    /// Folds constants and applies algebraic identities.
+    ///
+    /// Returns the simplified graph (which only has the nodes that the root
+    /// still depends on) and a list of the rewrites that were made. Use
+    /// [`evaluate`](#method.evaluate) to run the simplified expression with
+    /// other input values.
+    pub fn simplify(&self) -> (OpGraph, Vec<Simplification>) {
+        let mut out = OpGraph::new();
+        let mut map: Vec<NodeId> = Vec::with_capacity(self.len());
+        let mut report = Vec::new();
+        let mut memo = Compared::new();
+
+        for id in self.ids() {
+            let node = &self[id];
+            let ops: Vec<NodeId> = node.operands().iter().map(|o| map[o.idx()]).collect();
+
+            let new = match rewrite(&out, &mut memo, node, &ops) {
+                Some((rw, rule)) => {
+                    let mut relabeled = None;
+
+                    let new = match rw {
+                        Rewrite::Use(existing) => {
+                            // Keep the label around if we can; if we can't,
+                            // say what it's called now.
+                            match (node.label(), out[existing].label()) {
+                                (Some(label), None) => out.set_label(existing, label),
+                                (Some(old), Some(new)) if old != new => {
+                                    relabeled = Some((old, new))
+                                }
+                                _ => {}
+                            }
+
+                            existing
+                        }
+                        Rewrite::Const(val) => out.push_labeled(OpData::Immediate(), node.label(), val),
+                        Rewrite::Op(data) => out.push_labeled(data, node.label(), node.value()),
+                    };
+
+                    report.push(Simplification { node: id, rule, relabeled });
+                    new
+                }
+                None => out.push_labeled(node.data().with_operands(&ops), node.label(), node.value()),
+            };
+
+            map.push(new);
+        }
+
+        match map.last() {
+            Some(root) => (out.prune(*root), report),
+            None => (out, report),
+        }
+    }
+
+    fn push_labeled(&mut self, data: OpData, label: Option<&'static str>, value: Bits) -> NodeId {
+        let id = self.push(data, value);
+
+        if let Some(label) = label {
+            self.set_label(id, label);
+        }
+
+        id
+    }
+
+    /// A copy of this graph with only the nodes that `root` depends on.
+    fn prune(&self, root: NodeId) -> OpGraph {
+        let mut live = vec![false; self.len()];
+        live[root.idx()] = true;
+
+        // Users always come after their operands so one pass (backwards) is
+        // enough.
+        for id in self.ids().rev() {
+            if live[id.idx()] {
+                for o in self[id].operands() {
+                    live[o.idx()] = true;
+                }
+            }
+        }
+
+        let mut out = OpGraph::new();
+        let mut map: Vec<Option<NodeId>> = vec![None; self.len()];
+
+        for id in self.ids().filter(|id| live[id.idx()]) {
+            let node = &self[id];
+            let ops: Vec<NodeId> = node.operands().iter().map(|o| map[o.idx()].unwrap()).collect();
+
+            let new = out.push_labeled(node.data().with_operands(&ops), node.label(), node.value());
+            map[id.idx()] = Some(new);
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Op, OpWrapper, RegisterInput};
+    use super::super::imm::Imm;
+    use crate::state::Registers;
+
+    use core::cell::Cell;
+
+    struct Regs(Cell<u8>);
+
+    impl Registers for Regs {
+        type Word = u8;
+
+        fn read_reg(&self, _num: u8) -> u8 {
+            self.0.get()
+        }
+
+        fn write_reg(&self, _num: u8, word: u8) {
+            self.0.set(word)
+        }
+    }
+
+    fn imm(v: u8) -> OpWrapper<Imm<u8>, Regs> {
+        v.into()
+    }
+
+    #[test]
+    fn identities() {
+        let r = || RegisterInput::new::<Regs>(0);
+        let op = ((r() + imm(0)) & imm(255)) ^ !!(r() ^ imm(7));
+
+        let (v, g) = op.execute_with_graph(&Regs(Cell::new(3)));
+        assert_eq!(v, 7);
+
+        let (s, report) = g.simplify();
+        let rules: Vec<Rule> = report.iter().map(|s| s.rule).collect();
+
+        assert_eq!(rules, [Rule::AddZero, Rule::AndOnes, Rule::DoubleNot]);
+        assert_eq!(s.len(), 5);
+
+        // Same answer as the expression, for every input:
+        for x in 0..=255 {
+            let vals = s.evaluate(|_, _| Bits::new(8, u128::from(x)));
+            let expected = op.execute(&Regs(Cell::new(x)));
+            assert_eq!(vals[s.root().unwrap().idx()].val(), u128::from(expected));
+        }
+    }
+
+    #[test]
+    fn folding() {
+        let r = || RegisterInput::new::<Regs>(0);
+        let op = (r() ^ r()) | imm(5);
+
+        let (_, g) = op.execute_with_graph(&Regs(Cell::new(3)));
+        let (s, report) = g.simplify();
+        let rules: Vec<Rule> = report.iter().map(|s| s.rule).collect();
+
+        assert_eq!(rules, [Rule::XorSelf, Rule::ConstantFold]);
+        assert_eq!(s.len(), 1);
+        assert_eq!(s.nodes()[0].data(), &OpData::Immediate());
+        assert_eq!(s.nodes()[0].value(), Bits::new(8, 5));
+    }
+
+    #[test]
+    fn relabeling() {
+        let op = (RegisterInput::new::<Regs>(0).label("reg") + imm(0)).label("sum");
+
+        let (_, g) = op.execute_with_graph(&Regs(Cell::new(3)));
+        let (s, report) = g.simplify();
+
+        assert_eq!(report[0].relabeled, Some(("sum", "reg")));
+        assert_eq!(
+            report[0].to_string(),
+            "n2: adding or subtracting 0 does nothing (`sum` is now `reg`)"
+        );
+        assert_eq!(s[s.root().unwrap()].label(), Some("reg"));
+    }
+
+    #[test]
+    fn shared_subexpressions() {
+        // Two copies of x + x + x + ... that share every level; comparing
+        // them without remembering what was already compared takes 2^64
+        // steps.
+        let mut g = OpGraph::new();
+        let chain = |g: &mut OpGraph| {
+            let mut x = g.push(OpData::RegisterInput { num: 0 }, Bits::new(8, 0));
+            for _ in 0..64 {
+                x = g.push(OpData::Addition { lhs: x, rhs: x }, Bits::new(8, 0));
+            }
+
+            x
+        };
+
+        let (a, b) = (chain(&mut g), chain(&mut g));
+        let _ = g.push(OpData::Xor { lhs: a, rhs: b }, Bits::new(8, 0));
+
+        let (s, report) = g.simplify();
+        assert_eq!(report.last().map(|s| s.rule), Some(Rule::XorSelf));
+        assert_eq!(s.len(), 1);
+    }
+}
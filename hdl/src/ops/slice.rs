@@ -0,0 +1,110 @@
+use super::{Exit, Op, OpKind, OpWrapper, Param, Trace};
+use crate::bits::{Bits, FromBits, Value};
+use crate::wires::BitCountType;
+
+use core::marker::PhantomData;
+
+/// Takes bits `HI` down to `LO` (inclusive) of an op's output, producing a
+/// `W`.
+///
+/// Shows up in metadata like Verilog part selects: `ir[8:0]`.
+#[derive(Copy, Clone, Debug)]
+pub struct Slice<T, W, const HI: BitCountType, const LO: BitCountType> {
+    inp: T,
+    _out: PhantomData<W>,
+}
+
+impl<S, T, W, const HI: BitCountType, const LO: BitCountType> Op<S> for Slice<T, W, { HI }, { LO }>
+where
+    T: Op<S>,
+    W: FromBits,
+{
+    type Output = W;
+    const OPERATION: OpKind = OpKind::Slice;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        let inp = self.inp.execute(state).to_bits();
+
+        W::from_bits(Bits::new(W::WIDTH, inp.val() >> LO))
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::Slice, None);
+
+        let inp = self.inp.execute_with_trace(state, trace).to_bits();
+        let out = W::from_bits(Bits::new(W::WIDTH, inp.val() >> LO));
+
+        trace.exit(&Exit::new(OpKind::Slice, &out).with_param(Param::Range { hi: HI, lo: LO }));
+        out
+    }
+}
+
+impl<S, T: Op<S>> OpWrapper<T, S>
+where
+    T::Output: FromBits,
+{
+    /// Takes bits `HI` down to `LO` (inclusive) of this op's output:
+    /// `ir.slice::<8, 0, _>()`.
+    ///
+    /// The last parameter is the type of the slice; it can usually be
+    /// inferred. Since we can't do arithmetic on const generic parameters
+    /// (yet) this can't check that it's `HI - LO + 1` bits wide at compile
+    /// time so this panics if it isn't (or if the range doesn't fit in the
+    /// input).
+    pub fn slice<const HI: BitCountType, const LO: BitCountType, W>(
+        self,
+    ) -> OpWrapper<Slice<T, W, { HI }, { LO }>, S>
+    where
+        W: FromBits,
+    {
+        let from = <T::Output as FromBits>::WIDTH;
+
+        assert!(HI >= LO, "a slice's high bit ({}) can't be below its low bit ({})", HI, LO);
+        assert!(HI < from, "can't take bit {} of a {} bit value", HI, from);
+        assert_eq!(W::WIDTH, HI - LO + 1, "[{}:{}] is {} bits wide", HI, LO, HI - LO + 1);
+
+        Slice {
+            inp: self.unwrap(),
+            _out: PhantomData,
+        }.into()
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use super::super::{concat, imm::Imm};
+    use crate::w;
+    use crate::wires::Wire;
+
+    type W16 = Wire<{ 16 }, { 2 }>;
+
+    #[test]
+    fn fields() {
+        let ir: OpWrapper<Imm<W16>> = w!(16#0x1_2A5).into();
+        let (m, v) = ir.label("ir").slice::<8, 0, Wire<{ 9 }, { 2 }>>().execute_with_metadata(&());
+
+        assert_eq!(m, "ir=16'h12a5[8:0]");
+        assert_eq!(u16::from(v), 0x0A5);
+    }
+
+    #[test]
+    fn round_trip() {
+        let ir: OpWrapper<Imm<W16>> = w!(16#0xBEEF).into();
+        let ir = ir.share();
+
+        let hi = ir.clone().slice::<15, 4, Wire<{ 12 }, { 2 }>>();
+        let lo = ir.slice::<3, 0, Wire<{ 4 }, { 1 }>>();
+        let (m, v) = concat::<W16, _, _, _>(hi, lo).execute_with_metadata(&());
+
+        assert_eq!(m, "{16'hbeef[15:4], 16'hbeef[3:0]}");
+        assert_eq!(u16::from(v), 0xBEEF);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_width() {
+        let ir: OpWrapper<Imm<W16>> = w!(16#0).into();
+        let _ = ir.slice::<8, 0, Wire<{ 8 }, { 1 }>>();
+    }
+}
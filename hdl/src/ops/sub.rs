@@ -0,0 +1,80 @@
+use super::{Exit, Op, OpKind, OpWrapper, SameWidth, Trace};
+
+use core::ops::Sub as SubOp;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Sub<L, R> {
+    lhs: L,
+    rhs: R,
+}
+
+impl<S, L: Op<S>, R: Op<S>> Op<S> for Sub<L, R>
+where
+    L::Output: SameWidth<<R as Op<S>>::Output>,
+    L::Output: SubOp<Output = <L as Op<S>>::Output>,
+{
+    type Output = <L as Op<S>>::Output;
+    const OPERATION: OpKind = OpKind::Subtraction;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        let lhs = self.lhs.execute(state);
+        let rhs = L::Output::same_width(self.rhs.execute(state));
+
+        lhs - rhs
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::Subtraction, None);
+
+        let lhs = self.lhs.execute_with_trace(state, trace);
+        let rhs = L::Output::same_width(self.rhs.execute_with_trace(state, trace));
+        let out = lhs - rhs;
+
+        trace.exit(&Exit::new(OpKind::Subtraction, &out));
+        out
+    }
+}
+
+impl<S, L: Op<S>, R: Op<S>> SubOp<OpWrapper<R, S>> for OpWrapper<L, S>
+where
+    L::Output: SameWidth<<R as Op<S>>::Output>,
+    L::Output: SubOp<Output = <L as Op<S>>::Output>,
+{
+    type Output = OpWrapper<Sub<L, R>, S>;
+
+    fn sub(self, rhs: OpWrapper<R, S>) -> Self::Output {
+        Sub {
+            lhs: self.unwrap(),
+            rhs: rhs.unwrap(),
+        }.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::imm::Imm;
+    use crate::w;
+    use crate::wires::Wire;
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn integers() {
+        let a: OpWrapper<Imm<u16>> = 500u16.into();
+        let b: OpWrapper<Imm<u16>> = 200u16.into();
+
+        let (m, v) = (a - b).execute_with_metadata(&());
+
+        assert_eq!(m, "(500 - 200)");
+        assert_eq!(v, 300);
+    }
+
+    #[test]
+    fn wires_borrow() {
+        let a: OpWrapper<Imm<Wire<{ 8 }, { 1 }>>> = w!(8#0x13).into();
+        let b: OpWrapper<Imm<Wire<{ 8 }, { 1 }>>> = w!(8#0xF0).into();
+
+        // 0x13 - 0xF0 wraps around (borrowing out of the top bit):
+        assert_eq!(u8::from((a - b).execute(&())), 0x23);
+    }
+}
@@ -0,0 +1,274 @@
+//! Propagation delays and critical paths.
+//!
+//! Every kind of op gets a [`Delay`]: a function of the op's size (usually its
+//! width; see [`Delays::size`]) that gives how long the op takes to produce
+//! its output once its inputs are stable, in gate delays. [`Delays`] holds one
+//! for every kind of op, starting from defaults that model straightforward
+//! implementations (i.e. ripple-carry adders) and can be overridden to model
+//! others.
+//!
+//! [`OpGraph::critical_path`] is a static timing pass: it finds the path
+//! through the graph that takes the longest to settle.
+//!
+//! [`Delay`]: enum.Delay.html
+//! [`Delays`]: struct.Delays.html
+//! [`Delays::size`]: struct.Delays.html#method.size
+//! [`OpGraph::critical_path`]: struct.OpGraph.html#method.critical_path
+
+use super::{NodeId, OpGraph, OpKind};
+
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+
+/// `ceil(log2(n))`; 0 for 0 and 1.
+fn clog2(n: usize) -> u32 {
+    if n <= 1 {
+        0
+    } else {
+        0usize.count_zeros() - (n - 1).leading_zeros()
+    }
+}
+
+/// How long an op takes, as a function of its size `n`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Delay {
+    /// The same for every size.
+    Constant(u32),
+    /// `base + per_bit * n`; i.e. a ripple-carry adder.
+    Linear {
+        /// The part that doesn't depend on the size.
+        base: u32,
+        /// The added delay for every bit.
+        per_bit: u32,
+    },
+    /// `base + per_level * ceil(log2(n))`; i.e. a tree of gates or a
+    /// carry-lookahead adder.
+    Log {
+        /// The part that doesn't depend on the size.
+        base: u32,
+        /// The added delay for every level of the tree.
+        per_level: u32,
+    },
+}
+
+impl Delay {
+    /// The delay of an op of size `n`.
+    pub fn at(self, n: usize) -> u32 {
+        match self {
+            Delay::Constant(d) => d,
+            Delay::Linear { base, per_bit } => base + per_bit * n as u32,
+            Delay::Log { base, per_level } => base + per_level * clog2(n),
+        }
+    }
+}
+
+/// The delay of every kind of op.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delays {
+    overrides: Vec<(OpKind, Delay)>,
+}
+
+impl Default for Delays {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Delays {
+    /// The default delays:
+    ///   - inputs, immediates, and register outputs are ready immediately
+    ///   - ops that are just wiring (extensions, slices, concatenations) are
+    ///     free
+    ///   - bitwise ops take 1; 2-to-1 muxes take 2 and n-to-1 muxes take 2 per
+    ///     level of a tree of them
+    ///   - adders, subtracters, and magnitude comparisons ripple: 2 per bit
+    ///   - equality checks are a layer of `xor`s and then a tree of `or`s
+    ///   - memory reads decode the address: 2 plus 1 per address bit
+    pub fn new() -> Self {
+        Self { overrides: Vec::new() }
+    }
+
+    /// Replaces the delay for ops of kind `kind`.
+    pub fn with(mut self, kind: OpKind, delay: Delay) -> Self {
+        self.overrides.retain(|(k, _)| *k != kind);
+        self.overrides.push((kind, delay));
+        self
+    }
+
+    /// The delay for ops of kind `kind`.
+    pub fn get(&self, kind: OpKind) -> Delay {
+        use OpKind::*;
+
+        if let Some((_, d)) = self.overrides.iter().find(|(k, _)| *k == kind) {
+            return *d;
+        }
+
+        match kind {
+            Immediate | RegisterInput | Register | Input => Delay::Constant(0),
+            Zext | Sext | Slice | Concat => Delay::Constant(0),
+            And | Or | Xor | Not => Delay::Constant(1),
+            Mux => Delay::Constant(2),
+            MuxN => Delay::Log { base: 0, per_level: 2 },
+            Addition | Subtraction | Ult | Ule | Slt | Sle => Delay::Linear { base: 0, per_bit: 2 },
+            Eq | Ne => Delay::Log { base: 1, per_level: 1 },
+            MemoryInput | MemoryRead => Delay::Linear { base: 2, per_bit: 1 },
+        }
+    }
+
+    /// The size a node's delay is computed from: the number of inputs for
+    /// n-to-1 muxes, the address width for memory reads, and otherwise the
+    /// widest of the node's output and operands.
+    pub fn size(g: &OpGraph, id: NodeId) -> usize {
+        let node = &g[id];
+        let ops = node.operands();
+
+        match node.kind() {
+            OpKind::MuxN => ops.len() - 1,
+            OpKind::MemoryInput | OpKind::MemoryRead => g[ops[0]].value().width(),
+            _ => ops.iter().map(|o| g[*o].value().width()).fold(node.value().width(), core::cmp::max),
+        }
+    }
+
+    /// The delay of node `id` of `g`.
+    pub fn of(&self, g: &OpGraph, id: NodeId) -> u32 {
+        self.get(g[id].kind()).at(Self::size(g, id))
+    }
+}
+
+/// One node on a [`CriticalPath`](struct.CriticalPath.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PathStep {
+    /// The node.
+    pub node: NodeId,
+    /// What kind of op the node is.
+    pub kind: OpKind,
+    /// The node's label, if it has one.
+    pub label: Option<&'static str>,
+    /// The node's own delay.
+    pub delay: u32,
+    /// When the node's output settles (the total delay up to and including
+    /// this node).
+    pub arrival: u32,
+}
+
+/// The slowest path through a graph, from an input (or constant) to the node
+/// that settles last.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CriticalPath {
+    /// The nodes on the path, in order.
+    pub steps: Vec<PathStep>,
+}
+
+impl CriticalPath {
+    /// The total delay along the path.
+    pub fn delay(&self) -> u32 {
+        self.steps.last().map_or(0, |s| s.arrival)
+    }
+}
+
+impl OpGraph {
+    /// Finds the path through the graph that takes the longest to settle,
+    /// with the delay of each node given by `delays`. Ties go to the path that
+    /// ends (and then goes through) the earliest nodes.
+    pub fn critical_path(&self, delays: &Delays) -> CriticalPath {
+        let mut arrival: Vec<u32> = Vec::with_capacity(self.len());
+        let mut from: Vec<Option<NodeId>> = Vec::with_capacity(self.len());
+
+        for id in self.ids() {
+            let slowest = self[id].operands().into_iter().fold(None, |best: Option<NodeId>, o| match best {
+                Some(b) if arrival[b.idx()] >= arrival[o.idx()] => Some(b),
+                _ => Some(o),
+            });
+
+            arrival.push(slowest.map_or(0, |o| arrival[o.idx()]) + delays.of(self, id));
+            from.push(slowest);
+        }
+
+        let mut end = self.ids().fold(None, |best: Option<NodeId>, id| match best {
+            Some(b) if arrival[b.idx()] >= arrival[id.idx()] => Some(b),
+            _ => Some(id),
+        });
+
+        let mut steps = Vec::new();
+        while let Some(id) = end {
+            steps.push(PathStep {
+                node: id,
+                kind: self[id].kind(),
+                label: self[id].label(),
+                delay: delays.of(self, id),
+                arrival: arrival[id.idx()],
+            });
+            end = from[id.idx()];
+        }
+        steps.reverse();
+
+        CriticalPath { steps }
+    }
+}
+
+impl Display for CriticalPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>6} {:>6}  node", "delay", "total")?;
+
+        for s in self.steps.iter() {
+            write!(f, "{:>6} {:>6}  n{} {:?}", s.delay, s.arrival, s.node.idx(), s.kind)?;
+            match s.label {
+                Some(l) => writeln!(f, " ({})", l)?,
+                None => writeln!(f)?,
+            }
+        }
+
+        write!(f, "critical path: {}", self.delay())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Imm, Op, OpWrapper};
+
+    type U8 = OpWrapper<Imm<u8>>;
+
+    fn graph() -> OpGraph {
+        let (a, b, c): (U8, U8, U8) = (1.into(), 2.into(), 3.into());
+        let d: U8 = 4.into();
+
+        // n0 = a, n1 = b, n2 = a + b, n3 = c, n4 = (a + b) + c, n5 = d,
+        // n6 = ((a + b) + c) & d
+        ((a + b + c).label("sum") & d).execute_with_graph(&()).1
+    }
+
+    #[test]
+    fn ripple() {
+        let path = graph().critical_path(&Delays::new());
+        let nodes: Vec<usize> = path.steps.iter().map(|s| s.node.idx()).collect();
+
+        assert_eq!(nodes, [0, 2, 4, 6]);
+        assert_eq!(path.delay(), 16 + 16 + 1);
+    }
+
+    #[test]
+    fn lookahead() {
+        let delays = Delays::new().with(OpKind::Addition, Delay::Log { base: 2, per_level: 2 });
+        let path = graph().critical_path(&delays);
+
+        assert_eq!(path.delay(), 8 + 8 + 1);
+        assert_eq!(
+            path.to_string(),
+            " delay  total  node\n\
+            \x20    0      0  n0 Immediate\n\
+            \x20    8      8  n2 Addition\n\
+            \x20    8     16  n4 Addition (sum)\n\
+            \x20    1     17  n6 And\n\
+            critical path: 17"
+        );
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(clog2(1), 0);
+        assert_eq!(clog2(8), 3);
+        assert_eq!(clog2(9), 4);
+        assert_eq!(Delay::Linear { base: 1, per_bit: 2 }.at(8), 17);
+    }
+}
@@ -0,0 +1,280 @@
+//! Watching an expression get evaluated.
+//!
+//! [`Op::execute_with_trace`] reports every op it evaluates to a [`Trace`]:
+//! each op calls [`Trace::enter`] before it evaluates its operands and
+//! [`Trace::exit`] once it has produced its value. Operands are evaluated in
+//! order (`lhs` before `rhs`, the select line before the inputs of a mux) so
+//! the events for an op's operands always show up, in order, between that op's
+//! `enter` and `exit`.
+//!
+//! Ops that are [`Shared`] are only evaluated once per run. The first time one
+//! is used it's traced like any other op and then [`Trace::share`] is called;
+//! after that, uses of it only produce a [`Trace::reuse`] event. Shared ops
+//! find the run through the trace ([`Trace::run`]), so traces that wrap other
+//! traces have to pass it along.
+//!
+//! None of this needs a heap; the sinks that build `String`s or graphs out of
+//! the events live behind the `alloc` feature.
+//!
+//! [`Op::execute_with_trace`]: trait.Op.html#tymethod.execute_with_trace
+//! [`Trace`]: trait.Trace.html
+//! [`Trace::enter`]: trait.Trace.html#tymethod.enter
+//! [`Trace::exit`]: trait.Trace.html#tymethod.exit
+//! [`Trace::share`]: trait.Trace.html#tymethod.share
+//! [`Trace::reuse`]: trait.Trace.html#tymethod.reuse
+//! [`Trace::run`]: trait.Trace.html#method.run
+//! [`Shared`]: struct.Shared.html
+
+use super::OpKind;
+#[cfg(feature = "alloc")]
+use super::Run;
+use crate::bits::{Bits, Value};
+use crate::wires::BitCountType;
+
+use core::fmt::{self, Debug, Display};
+
+/// The parts of an op that aren't its operands or its value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Param {
+    /// Nothing; most ops don't have any parameters.
+    None,
+    /// The register a `RegisterInput` reads.
+    Register(u8),
+    /// The name of the state element, memory, or port (i.e. a `Register` or an
+    /// `Input`) an op reads.
+    Name(&'static str),
+    /// The (inclusive) range of bits a `Slice` takes.
+    Range {
+        /// The most significant bit.
+        hi: BitCountType,
+        /// The least significant bit.
+        lo: BitCountType,
+    },
+}
+
+/// Everything a [`Trace`](trait.Trace.html) gets told about an op once it has
+/// been evaluated.
+#[derive(Copy, Clone)]
+pub struct Exit<'a> {
+    /// The kind of op.
+    pub kind: OpKind,
+    /// The op's label, if it has one.
+    pub label: Option<&'static str>,
+    /// The op's parameters.
+    pub param: Param,
+    /// The value the op produced.
+    pub value: Bits,
+    /// The value as printed by its own type. Only ops whose outputs have to be
+    /// `Display` (immediates and inputs) provide this.
+    pub display: Option<&'a dyn Display>,
+}
+
+impl<'a> Exit<'a> {
+    /// An `Exit` for an op of kind `kind` that produced `value`.
+    pub fn new(kind: OpKind, value: &impl Value) -> Self {
+        Exit {
+            kind,
+            label: None,
+            param: Param::None,
+            value: value.to_bits(),
+            display: None,
+        }
+    }
+
+    /// Sets the `Display`ed form of the value.
+    pub fn with_display(mut self, display: &'a dyn Display) -> Self {
+        self.display = Some(display);
+        self
+    }
+
+    /// Sets the op's parameters.
+    pub fn with_param(mut self, param: Param) -> Self {
+        self.param = param;
+        self
+    }
+}
+
+impl Debug for Exit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Exit")
+            .field("kind", &self.kind)
+            .field("label", &self.label)
+            .field("param", &self.param)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+/// Receives events as an expression is evaluated.
+pub trait Trace {
+    /// An op is about to evaluate its operands.
+    fn enter(&mut self, kind: OpKind, label: Option<&'static str>);
+
+    /// An op has produced its value. Every `exit` matches the most recent
+    /// `enter` that hasn't been matched yet.
+    fn exit(&mut self, node: &Exit<'_>);
+
+    /// The op that just exited is shared and may be used again later in this
+    /// run; `key` identifies it.
+    fn share(&mut self, key: usize);
+
+    /// A shared op that has already exited during this run was used again.
+    ///
+    /// It isn't evaluated again so this takes the place of its `enter` and
+    /// `exit` (and those of its operands). `node` is the same as it was at the
+    /// op's `exit` except for the label, which is the label at this use.
+    fn reuse(&mut self, key: usize, node: &Exit<'_>);
+
+    /// The run the expression is being evaluated in, if any; shared ops save
+    /// their outputs in it.
+    ///
+    /// `OpWrapper`s start a run for traces that don't have one so most traces
+    /// don't need to implement this, but traces that wrap another trace should
+    /// return the inner trace's run.
+    #[cfg(feature = "alloc")]
+    fn run(&mut self) -> Option<&mut Run> {
+        None
+    }
+
+    /// Whether anything is listening to the events.
+    ///
+    /// Muxes evaluate the inputs they don't pick too, so that traces see the
+    /// whole expression, but skip them for traces that ignore everything.
+    /// Traces that wrap another trace should ask the inner trace.
+    fn watching(&self) -> bool {
+        true
+    }
+}
+
+/// A trace that ignores everything.
+impl Trace for () {
+    fn enter(&mut self, _kind: OpKind, _label: Option<&'static str>) {}
+    fn exit(&mut self, _node: &Exit<'_>) {}
+    fn share(&mut self, _key: usize) {}
+    fn reuse(&mut self, _key: usize, _node: &Exit<'_>) {}
+
+    fn watching(&self) -> bool {
+        false
+    }
+}
+
+/// Attaches a label to the first op that passes through it (and passes all
+/// events along to another trace).
+///
+/// This is how `LabeledOp`s get their labels into traces without having nodes
+/// of their own. If the op already has a label (i.e. it was labeled twice), the
+/// outermost label wins.
+pub(crate) struct Labeled<'t> {
+    inner: &'t mut dyn Trace,
+    label: &'static str,
+    depth: usize,
+}
+
+impl<'t> Labeled<'t> {
+    pub(crate) fn new(inner: &'t mut dyn Trace, label: &'static str) -> Self {
+        Labeled { inner, label, depth: 0 }
+    }
+}
+
+impl Debug for Labeled<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Labeled")
+            .field("label", &self.label)
+            .field("depth", &self.depth)
+            .finish()
+    }
+}
+
+impl Trace for Labeled<'_> {
+    fn enter(&mut self, kind: OpKind, label: Option<&'static str>) {
+        let label = if self.depth == 0 { Some(self.label) } else { label };
+        self.depth += 1;
+
+        self.inner.enter(kind, label)
+    }
+
+    fn exit(&mut self, node: &Exit<'_>) {
+        self.depth -= 1;
+
+        if self.depth == 0 {
+            self.inner.exit(&Exit { label: Some(self.label), ..*node })
+        } else {
+            self.inner.exit(node)
+        }
+    }
+
+    fn share(&mut self, key: usize) {
+        self.inner.share(key)
+    }
+
+    fn reuse(&mut self, key: usize, node: &Exit<'_>) {
+        if self.depth == 0 {
+            self.inner.reuse(key, &Exit { label: Some(self.label), ..*node })
+        } else {
+            self.inner.reuse(key, node)
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn run(&mut self) -> Option<&mut Run> {
+        self.inner.run()
+    }
+
+    fn watching(&self) -> bool {
+        self.inner.watching()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Op, OpWrapper};
+    use super::super::imm::Imm;
+
+    /// Counts events and checks that they nest.
+    #[derive(Debug, Default)]
+    struct Counter {
+        depth: usize,
+        max_depth: usize,
+        exits: usize,
+        labels: usize,
+    }
+
+    impl Trace for Counter {
+        fn enter(&mut self, _kind: OpKind, _label: Option<&'static str>) {
+            self.depth += 1;
+            self.max_depth = self.max_depth.max(self.depth);
+        }
+
+        fn exit(&mut self, node: &Exit<'_>) {
+            self.depth -= 1;
+            self.exits += 1;
+
+            if node.label.is_some() {
+                self.labels += 1;
+            }
+        }
+
+        fn share(&mut self, _key: usize) {}
+
+        fn reuse(&mut self, _key: usize, _node: &Exit<'_>) {
+            self.exits += 1;
+        }
+    }
+
+    #[test]
+    fn events_nest() {
+        let a: OpWrapper<Imm<u8>> = 1u8.into();
+        let b: OpWrapper<Imm<u8>> = 2u8.into();
+        let c: OpWrapper<Imm<u8>> = 3u8.into();
+
+        let mut counter = Counter::default();
+        let v = ((a + b).label("sum") ^ !c).execute_with_trace(&(), &mut counter);
+
+        assert_eq!(v, 3 ^ !3);
+        assert_eq!(counter.depth, 0);
+        assert_eq!(counter.max_depth, 3);
+        assert_eq!(counter.exits, 6);
+        assert_eq!(counter.labels, 1);
+    }
+}
@@ -0,0 +1,70 @@
+use super::{Exit, Op, OpKind, OpWrapper, SameWidth, Trace};
+
+use core::ops::BitXor as BitXorOp;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Xor<L, R> {
+    lhs: L,
+    rhs: R,
+}
+
+impl<S, L: Op<S>, R: Op<S>> Op<S> for Xor<L, R>
+where
+    L::Output: SameWidth<<R as Op<S>>::Output>,
+    L::Output: BitXorOp<Output = <L as Op<S>>::Output>,
+{
+    type Output = <L as Op<S>>::Output;
+    const OPERATION: OpKind = OpKind::Xor;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        let lhs = self.lhs.execute(state);
+        let rhs = L::Output::same_width(self.rhs.execute(state));
+
+        lhs ^ rhs
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::Xor, None);
+
+        let lhs = self.lhs.execute_with_trace(state, trace);
+        let rhs = L::Output::same_width(self.rhs.execute_with_trace(state, trace));
+        let out = lhs ^ rhs;
+
+        trace.exit(&Exit::new(OpKind::Xor, &out));
+        out
+    }
+}
+
+impl<S, L: Op<S>, R: Op<S>> BitXorOp<OpWrapper<R, S>> for OpWrapper<L, S>
+where
+    L::Output: SameWidth<<R as Op<S>>::Output>,
+    L::Output: BitXorOp<Output = <L as Op<S>>::Output>,
+{
+    type Output = OpWrapper<Xor<L, R>, S>;
+
+    fn bitxor(self, rhs: OpWrapper<R, S>) -> Self::Output {
+        Xor {
+            lhs: self.unwrap(),
+            rhs: rhs.unwrap(),
+        }.into()
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use super::super::imm::Imm;
+
+    #[test]
+    fn bitwise_expression() {
+        let a: OpWrapper<Imm<u8>> = 0b1100u8.into();
+        let b: OpWrapper<Imm<u8>> = 0b0110u8.into();
+        let c: OpWrapper<Imm<u8>> = 0b1111_0000u8.into();
+        let d: OpWrapper<Imm<u8>> = 0b0000_0001u8.into();
+
+        let (m, v) = (((a ^ b) & !c) | d).execute_with_metadata(&());
+
+        assert_eq!(m, "(((12 ^ 6) & !240) | 1)");
+        assert_eq!(v, 0b1011);
+    }
+}
@@ -0,0 +1,98 @@
+use super::{Exit, Op, OpKind, OpWrapper, Trace};
+use crate::bits::Value;
+use crate::wires::{BitCountType, Extend, Width, WireOfWidth};
+
+use core::marker::PhantomData;
+
+/// Zero extends the output of an op to the wire type `W`.
+#[derive(Copy, Clone, Debug)]
+pub struct Zext<T, W> {
+    inp: T,
+    _out: PhantomData<W>,
+}
+
+impl<S, T: Op<S>, W> Op<S> for Zext<T, W>
+where
+    T::Output: Extend<W>,
+    W: Value,
+{
+    type Output = W;
+    const OPERATION: OpKind = OpKind::Zext;
+
+    fn execute(&self, state: &S) -> Self::Output {
+        self.inp.execute(state).zext()
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> Self::Output {
+        trace.enter(OpKind::Zext, None);
+
+        let out = self.inp.execute_with_trace(state, trace).zext();
+
+        trace.exit(&Exit::new(OpKind::Zext, &out));
+        out
+    }
+}
+
+impl<S, T: Op<S>> OpWrapper<T, S> {
+    /// Zero extends this op's output to `N` bits.
+    pub fn zext<const N: BitCountType>(self) -> OpWrapper<Zext<T, <Width<{ N }> as WireOfWidth>::Wire>, S>
+    where
+        Width<{ N }>: WireOfWidth,
+        T::Output: Extend<<Width<{ N }> as WireOfWidth>::Wire>,
+    {
+        let (from, to) = (
+            <T::Output as Extend<<Width<{ N }> as WireOfWidth>::Wire>>::FROM,
+            <T::Output as Extend<<Width<{ N }> as WireOfWidth>::Wire>>::TO,
+        );
+        assert!(to >= from, "can't zero extend a {} bit wire to {} bits", from, to);
+
+        Zext {
+            inp: self.unwrap(),
+            _out: PhantomData,
+        }.into()
+    }
+}
+
+/// Zero extends the output of `op` to `N` bits: `zext::<16, _>(a)`.
+///
+/// The second parameter is the type of the context; Rust won't let us specify
+/// just the first generic parameter of a function so it has to be spelled out
+/// (as `_`). `a.zext::<16>()` doesn't have this problem.
+pub fn zext<const N: BitCountType, S>(
+    op: OpWrapper<impl Op<S, Output = impl Extend<<Width<{ N }> as WireOfWidth>::Wire> + Value>, S>,
+) -> OpWrapper<impl Op<S, Output = <Width<{ N }> as WireOfWidth>::Wire>, S>
+where
+    Width<{ N }>: WireOfWidth,
+{
+    op.zext::<{ N }>()
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use super::super::{imm::Imm, mux_n};
+    use crate::w;
+    use crate::wires::Wire;
+
+    type W8 = Wire<{ 8 }, { 1 }>;
+
+    #[test]
+    fn extends() {
+        let a: OpWrapper<Imm<W8>> = w!(8#0x80).into();
+        let (m, v) = a.zext::<16>().execute_with_metadata(&());
+
+        assert_eq!(m, "zext16(8'h80)");
+        assert_eq!(u16::from(v), 0x0080);
+    }
+
+    #[test]
+    fn wire_selects() {
+        let inputs: [OpWrapper<Imm<W8>>; 4] = [w!(8#1).into(), w!(8#2).into(), w!(8#3).into(), w!(8#4).into()];
+        let sel: OpWrapper<Imm<Wire<{ 2 }, { 1 }>>> = w!(2#2).into();
+
+        let (m, v) = mux_n(sel, inputs).zext::<12>().execute_with_metadata(&());
+
+        assert_eq!(m, "zext12(case(2'h2){0: 8'h01, 1: 8'h02, 2: [8'h03], 3: 8'h04})");
+        assert_eq!(u16::from(v), 3);
+    }
+}
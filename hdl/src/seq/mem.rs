@@ -0,0 +1,476 @@
+//! Memory blocks: arrays of wires indexed by an address wire.
+//!
+//! A memory with `A` address bits has `2^A` locations, each `D` bits wide.
+//! Reads are ops ([`MemoryRead`]) so they can be used anywhere in an
+//! expression; they're combinational (the value shows up in the same cycle as
+//! the address) unless they go through a [registered read
+//! port](struct.Ram.html#method.registered_read).
+//!
+//! [`MemoryRead`]: struct.MemoryRead.html
+
+use super::{AsBits, Asserted, Clocked, Next, Register, RegisterOutput, Signal};
+use crate::bits::{Bits, FromBits, Value};
+use crate::export::{Design, MemoryDesc, WritePortDesc};
+use crate::ops::{Bit, Exit, Op, OpKind, OpWrapper, Param, Trace};
+use crate::wires::{BitCountType, Width, WireOfWidth};
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::fmt::{self, Debug, Display};
+
+/// The contents of a memory, shared by the memory and all of its read ops.
+type Cells<W> = Rc<[Cell<W>]>;
+
+/// `2^a` locations, all holding `init`.
+fn cells<W: Copy>(a: BitCountType, init: W) -> Cells<W> {
+    (0..(1usize << a)).map(|_| Cell::new(init)).collect::<Vec<_>>().into()
+}
+
+fn zero<W: FromBits>() -> W {
+    W::from_bits(Bits::new(W::WIDTH, 0))
+}
+
+/// A read-only memory with `A` address bits and `D` bit words.
+pub struct Rom<const A: BitCountType, const D: BitCountType>
+where
+    Width<{ A }>: WireOfWidth,
+    Width<{ D }>: WireOfWidth,
+{
+    name: &'static str,
+    cells: Cells<<Width<{ D }> as WireOfWidth>::Wire>,
+}
+
+impl<const A: BitCountType, const D: BitCountType> Rom<{ A }, { D }>
+where
+    Width<{ A }>: WireOfWidth,
+    Width<{ D }>: WireOfWidth,
+{
+    /// A ROM named `name` that holds `contents`, starting at address 0. Any
+    /// locations past the end of `contents` hold zeros.
+    ///
+    /// Panics if `contents` doesn't fit.
+    pub fn new(name: &'static str, contents: &[<Width<{ D }> as WireOfWidth>::Wire]) -> Self {
+        assert!(
+            contents.len() <= 1 << A,
+            "`{}` only has {} locations; can't fit {} words", name, 1usize << A, contents.len()
+        );
+
+        let cells = cells(A, zero());
+        for (cell, word) in cells.iter().zip(contents.iter()) {
+            cell.set(*word);
+        }
+
+        Self { name, cells }
+    }
+
+    /// An op that reads the location `addr` points to.
+    pub fn read<S, T>(&self, addr: OpWrapper<T, S>) -> OpWrapper<MemoryRead<T, <Width<{ D }> as WireOfWidth>::Wire>, S>
+    where
+        T: Op<S, Output = <Width<{ A }> as WireOfWidth>::Wire>,
+    {
+        MemoryRead { name: self.name, addr: addr.unwrap(), cells: self.cells.clone() }.into()
+    }
+
+    /// The word at `addr`.
+    pub fn get(&self, addr: usize) -> <Width<{ D }> as WireOfWidth>::Wire {
+        self.cells[addr].get()
+    }
+}
+
+/// ROMs don't change on clock edges but they're state elements all the same;
+/// add them to a [`Simulator`](struct.Simulator.html) so that they show up in
+/// its [`Design`](../export/struct.Design.html).
+impl<S, const A: BitCountType, const D: BitCountType> Clocked<S> for Rom<{ A }, { D }>
+where
+    Width<{ A }>: WireOfWidth,
+    Width<{ D }>: WireOfWidth,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn value(&self) -> Option<Bits> {
+        None
+    }
+
+    fn settle(&self, _state: &S) {}
+
+    fn sample(&self, _state: &S, _trace: &mut dyn Trace) {}
+
+    fn update(&self) {}
+
+    fn describe(&self, _state: &S, design: &mut Design) {
+        design.memories.push(MemoryDesc {
+            name: self.name,
+            addr_width: A,
+            data_width: D,
+            contents: self.cells.iter().map(|c| c.get().to_bits()).collect(),
+            writes: Vec::new(),
+        });
+    }
+}
+
+impl<const A: BitCountType, const D: BitCountType> Clone for Rom<{ A }, { D }>
+where
+    Width<{ A }>: WireOfWidth,
+    Width<{ D }>: WireOfWidth,
+{
+    fn clone(&self) -> Self {
+        Self { name: self.name, cells: self.cells.clone() }
+    }
+}
+
+impl<const A: BitCountType, const D: BitCountType> Debug for Rom<{ A }, { D }>
+where
+    Width<{ A }>: WireOfWidth,
+    Width<{ D }>: WireOfWidth,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rom")
+            .field("name", &self.name)
+            .field("addr_width", &A)
+            .field("data_width", &D)
+            .finish()
+    }
+}
+
+struct WritePort<S, W> {
+    addr: Box<dyn Signal<S, Bits>>,
+    data: Box<dyn Signal<S, W>>,
+    enable: Box<dyn Signal<S, bool>>,
+}
+
+struct RamInner<S, W> {
+    name: &'static str,
+    cells: Cells<W>,
+    writes: RefCell<Vec<WritePort<S, W>>>,
+    /// The writes from the last call to `sample`, in port order.
+    pending: RefCell<Vec<(usize, W)>>,
+    /// Registered read ports; the RAM clocks these along with itself.
+    reads: RefCell<Vec<Box<dyn Clocked<S>>>>,
+}
+
+/// A read/write memory with `A` address bits and `D` bit words.
+///
+/// RAMs can have any number of write ports (added with
+/// [`write_port`](#method.write_port)). Writes are synchronous: every port
+/// whose enable is asserted writes on the clock edge. If more than one port
+/// writes the same location in a cycle, the port that was added last wins.
+///
+/// Reads are combinational ([`read`](#method.read)) or registered
+/// ([`registered_read`](#method.registered_read)). Registered reads sample the
+/// memory on the clock edge, before that edge's writes happen.
+///
+/// RAMs start out holding zeros. Like registers, RAMs have to be clocked (i.e.
+/// added to a [`Simulator`](struct.Simulator.html)) and handles are cheap to
+/// clone and all refer to the same RAM.
+pub struct Ram<S, const A: BitCountType, const D: BitCountType>
+where
+    Width<{ A }>: WireOfWidth,
+    Width<{ D }>: WireOfWidth,
+{
+    inner: Rc<RamInner<S, <Width<{ D }> as WireOfWidth>::Wire>>,
+}
+
+impl<S, const A: BitCountType, const D: BitCountType> Ram<S, { A }, { D }>
+where
+    Width<{ A }>: WireOfWidth,
+    Width<{ D }>: WireOfWidth,
+{
+    /// A RAM named `name`, with no ports.
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            inner: Rc::new(RamInner {
+                name,
+                cells: cells(A, zero()),
+                writes: RefCell::new(Vec::new()),
+                pending: RefCell::new(Vec::new()),
+                reads: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Adds a write port that writes `data` to `addr` on clock edges where
+    /// `enable` is asserted.
+    pub fn write_port<T, U, E>(&self, addr: OpWrapper<T, S>, data: OpWrapper<U, S>, enable: OpWrapper<E, S>) -> &Self
+    where
+        T: Op<S, Output = <Width<{ A }> as WireOfWidth>::Wire> + 'static,
+        U: Op<S, Output = <Width<{ D }> as WireOfWidth>::Wire> + 'static,
+        E: Op<S> + 'static,
+        E::Output: Bit,
+        S: 'static,
+    {
+        self.inner.writes.borrow_mut().push(WritePort {
+            addr: Box::new(AsBits(addr)),
+            data: Box::new(Next(data)),
+            enable: Box::new(Asserted(enable)),
+        });
+        self
+    }
+
+    /// An op that reads the location `addr` points to, in the same cycle.
+    pub fn read<T>(&self, addr: OpWrapper<T, S>) -> OpWrapper<MemoryRead<T, <Width<{ D }> as WireOfWidth>::Wire>, S>
+    where
+        T: Op<S, Output = <Width<{ A }> as WireOfWidth>::Wire>,
+    {
+        MemoryRead { name: self.inner.name, addr: addr.unwrap(), cells: self.inner.cells.clone() }.into()
+    }
+
+    /// Adds a registered read port: a register, named `name`, that takes the
+    /// value at `addr` on every clock edge. Returns the register's output.
+    pub fn registered_read<T>(
+        &self,
+        name: &'static str,
+        addr: OpWrapper<T, S>,
+    ) -> OpWrapper<RegisterOutput<<Width<{ D }> as WireOfWidth>::Wire>, S>
+    where
+        T: Op<S, Output = <Width<{ A }> as WireOfWidth>::Wire> + 'static,
+        S: 'static,
+    {
+        let reg: Register<S, { D }> = Register::new(name, zero());
+        let _ = reg.next(self.read(addr));
+
+        let out = reg.out();
+        self.inner.reads.borrow_mut().push(Box::new(reg));
+        out
+    }
+
+    /// The word at `addr`.
+    pub fn get(&self, addr: usize) -> <Width<{ D }> as WireOfWidth>::Wire {
+        self.inner.cells[addr].get()
+    }
+
+    /// Overwrites the word at `addr`, bypassing the write ports.
+    pub fn set(&self, addr: usize, word: <Width<{ D }> as WireOfWidth>::Wire) {
+        self.inner.cells[addr].set(word)
+    }
+
+    /// Overwrites the RAM's contents, starting at address 0, with `contents`.
+    ///
+    /// Panics if `contents` doesn't fit.
+    pub fn load(&self, contents: &[<Width<{ D }> as WireOfWidth>::Wire]) {
+        assert!(
+            contents.len() <= self.inner.cells.len(),
+            "`{}` only has {} locations; can't fit {} words", self.inner.name, self.inner.cells.len(), contents.len()
+        );
+
+        for (addr, word) in contents.iter().enumerate() {
+            self.set(addr, *word);
+        }
+    }
+}
+
+impl<S, const A: BitCountType, const D: BitCountType> Clocked<S> for Ram<S, { A }, { D }>
+where
+    Width<{ A }>: WireOfWidth,
+    Width<{ D }>: WireOfWidth,
+{
+    fn name(&self) -> &'static str {
+        self.inner.name
+    }
+
+    fn value(&self) -> Option<Bits> {
+        None
+    }
+
+    fn signals(&self) -> Vec<(&'static str, Bits)> {
+        self.inner.reads.borrow().iter().flat_map(|r| r.signals()).collect()
+    }
+
+    fn settle(&self, state: &S) {
+        for r in self.inner.reads.borrow().iter() {
+            r.settle(state);
+        }
+    }
+
+    fn sample(&self, state: &S, trace: &mut dyn Trace) {
+        let mut writes = Vec::new();
+        for w in self.inner.writes.borrow().iter() {
+            let enable = w.enable.eval(state, trace);
+            let (addr, data) = (w.addr.eval(state, trace), w.data.eval(state, trace));
+
+            if enable {
+                writes.push((addr.val() as usize, data));
+            }
+        }
+
+        *self.inner.pending.borrow_mut() = writes;
+
+        for r in self.inner.reads.borrow().iter() {
+            r.sample(state, trace);
+        }
+    }
+
+    fn update(&self) {
+        for r in self.inner.reads.borrow().iter() {
+            r.update();
+        }
+
+        for (addr, word) in self.inner.pending.borrow_mut().drain(..) {
+            self.inner.cells[addr].set(word);
+        }
+    }
+
+    fn describe(&self, state: &S, design: &mut Design) {
+        let inner = &*self.inner;
+
+        design.memories.push(MemoryDesc {
+            name: inner.name,
+            addr_width: A,
+            data_width: D,
+            contents: inner.cells.iter().map(|c| c.get().to_bits()).collect(),
+            writes: inner
+                .writes
+                .borrow()
+                .iter()
+                .map(|w| WritePortDesc {
+                    addr: w.addr.graph(state),
+                    data: w.data.graph(state),
+                    enable: w.enable.graph(state),
+                })
+                .collect(),
+        });
+
+        for r in inner.reads.borrow().iter() {
+            r.describe(state, design);
+        }
+    }
+}
+
+impl<S, const A: BitCountType, const D: BitCountType> Clone for Ram<S, { A }, { D }>
+where
+    Width<{ A }>: WireOfWidth,
+    Width<{ D }>: WireOfWidth,
+{
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl<S, const A: BitCountType, const D: BitCountType> Debug for Ram<S, { A }, { D }>
+where
+    Width<{ A }>: WireOfWidth,
+    Width<{ D }>: WireOfWidth,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ram")
+            .field("name", &self.inner.name)
+            .field("addr_width", &A)
+            .field("data_width", &D)
+            .field("write_ports", &self.inner.writes.borrow().len())
+            .field("registered_reads", &self.inner.reads.borrow().len())
+            .finish()
+    }
+}
+
+/// Reads a [`Rom`](struct.Rom.html) or a [`Ram`](struct.Ram.html) at the
+/// address produced by an op.
+///
+/// Shows up in metadata as the memory's name, the address, and the value that
+/// was read, i.e. `<rom[8'h03]=16'h1234>`.
+#[derive(Clone)]
+pub struct MemoryRead<T, W> {
+    name: &'static str,
+    addr: T,
+    cells: Cells<W>,
+}
+
+impl<S, T: Op<S>, W: Copy + Display + Value> Op<S> for MemoryRead<T, W> {
+    type Output = W;
+    const OPERATION: OpKind = OpKind::MemoryRead;
+
+    fn execute(&self, state: &S) -> W {
+        self.cells[self.addr.execute(state).to_bits().val() as usize].get()
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> W {
+        trace.enter(OpKind::MemoryRead, None);
+
+        let addr = self.addr.execute_with_trace(state, trace);
+        let val = self.cells[addr.to_bits().val() as usize].get();
+
+        trace.exit(&Exit::new(OpKind::MemoryRead, &val).with_display(&val).with_param(Param::Name(self.name)));
+        val
+    }
+}
+
+impl<T: Debug, W> Debug for MemoryRead<T, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryRead")
+            .field("name", &self.name)
+            .field("addr", &self.addr)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::Imm;
+    use crate::seq::{Input, Simulator};
+    use crate::w;
+    use crate::wires::Wire;
+
+    type W8 = Wire<{ 8 }, { 1 }>;
+
+    #[test]
+    fn rom() {
+        let rom: Rom<{ 2 }, { 8 }> = Rom::new("sq", &[w!(8#0), w!(8#1), w!(8#4)]);
+        let addr: Input<{ 2 }> = Input::new("addr", w!(2#2));
+
+        let (m, v) = rom.read::<(), _>(addr.out()).execute_with_metadata(&());
+        assert_eq!(m, "<sq[<addr=2'h2>]=8'h04>");
+        assert_eq!(u8::from(v), 4);
+
+        addr.drive(w!(2#3));
+        assert_eq!(u8::from(rom.read::<(), _>(addr.out()).execute(&())), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rom_too_small() {
+        let _: Rom<{ 1 }, { 8 }> = Rom::new("r", &[w!(8#0), w!(8#1), w!(8#2)]);
+    }
+
+    #[test]
+    fn ram() {
+        let ram: Ram<(), { 4 }, { 8 }> = Ram::new("ram");
+        let addr: Input<{ 4 }> = Input::new("addr", w!(4#1));
+        let data: Input<{ 8 }> = Input::new("data", w!(8#0xAA));
+        let we: Input<{ 1 }> = Input::new("we", w!(1#1));
+        let we2: Input<{ 1 }> = Input::new("we2", w!(1#0));
+
+        let ff: OpWrapper<Imm<W8>> = w!(8#0xFF).into();
+        let _ = ram
+            .write_port(addr.out(), data.out(), we.out())
+            .write_port(addr.out(), ff, we2.out());
+
+        let comb = ram.read(addr.out());
+        let reg = ram.registered_read("q", addr.out());
+
+        let mut sim = Simulator::new(());
+        let _ = sim.add_clocked(ram.clone()).add_input(we2);
+
+        // Combinational reads see the write right after the edge; registered
+        // reads saw the old value on that edge.
+        sim.step();
+        assert_eq!(u8::from(sim.eval(&comb)), 0xAA);
+        assert_eq!(u8::from(sim.eval(&reg)), 0x00);
+
+        sim.step();
+        assert_eq!(u8::from(sim.eval(&reg)), 0xAA);
+
+        // The port added last wins.
+        sim.drive("we2", w!(1#1));
+        sim.step();
+        assert_eq!(u8::from(ram.get(1)), 0xFF);
+        assert_eq!(sim.value("ram"), None);
+
+        // Registered reads are registers like any other.
+        assert_eq!(sim.value("q"), Some(Bits::new(8, 0xAA)));
+        sim.step();
+        assert_eq!(sim.value("q"), Some(Bits::new(8, 0xFF)));
+    }
+}
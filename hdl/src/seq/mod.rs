@@ -0,0 +1,133 @@
+//! Sequential logic: state elements that only change on clock edges.
+//!
+//! Everything in [`ops`](../ops/index.html) is combinational; an expression's
+//! value depends only on its inputs. The elements in here hold a value from
+//! one clock cycle to the next and are read, within expressions, through op
+//! leaves (i.e. [`Register::out`]).
+//!
+//! Clocking happens in two phases so that elements that feed each other (i.e.
+//! two registers that swap values) all see the values from _before_ the
+//! clock edge: first every element [samples](trait.Clocked.html#tymethod.sample)
+//! its inputs and only then does every element
+//! [update](trait.Clocked.html#tymethod.update) its output.
+//!
+//! A [`Simulator`] owns a design's state and its state elements and clocks
+//! them all together, one cycle at a time. Designs get their inputs from
+//! [`Input`] ports, which can be driven between cycles.
+//!
+//! Addressable arrays of wires are modeled by [`Rom`] and [`Ram`].
+//!
+//! [`Register::out`]: struct.Register.html#method.out
+//! [`Simulator`]: struct.Simulator.html
+//! [`Input`]: struct.Input.html
+//! [`Rom`]: struct.Rom.html
+//! [`Ram`]: struct.Ram.html
+
+mod mem;
+mod port;
+mod register;
+mod sim;
+mod vcd;
+
+pub use mem::{MemoryRead, Ram, Rom};
+pub use port::{Drivable, Input, InputPort};
+pub use register::{Register, RegisterOutput, Reset};
+pub use sim::Simulator;
+
+use crate::bits::{Bits, Value};
+use crate::export::Design;
+use crate::ops::{Bit, Op, OpGraph, OpWrapper, Selector, Trace};
+
+use alloc::vec::Vec;
+
+/// A state element that's driven by a clock.
+///
+/// This is object safe so that a design's state elements can be kept together
+/// (i.e. as `Vec<Box<dyn Clocked<S>>>`) regardless of their widths.
+pub trait Clocked<S> {
+    /// The element's name, as it shows up in expressions.
+    fn name(&self) -> &'static str;
+
+    /// The element's current value, for elements that have a single value
+    /// (i.e. not memories).
+    fn value(&self) -> Option<Bits>;
+
+    /// The name and current value of every register in the element: the
+    /// element itself, if it has a value, and any registers it clocks along
+    /// with itself (i.e. a RAM's registered read ports).
+    fn signals(&self) -> Vec<(&'static str, Bits)> {
+        self.value().map(|val| (self.name(), val)).into_iter().collect()
+    }
+
+    /// Applies anything that doesn't wait for the clock (asynchronous resets).
+    fn settle(&self, state: &S);
+
+    /// Evaluates the element's inputs and works out the value it'll have after
+    /// the clock edge. The element's output doesn't change yet; if it's
+    /// sampled again first, only the last sample counts.
+    ///
+    /// All of the element's inputs are evaluated with `trace` (even ones that
+    /// don't matter this cycle, like the next value of a disabled register) so
+    /// that it sees all of the logic. Every element is sampled with the same
+    /// run (see [`Trace::run`](../ops/trait.Trace.html#method.run)) so that
+    /// logic that feeds more than one of them is only evaluated once per clock
+    /// edge.
+    fn sample(&self, state: &S, trace: &mut dyn Trace);
+
+    /// Makes the value from the last call to `sample` the element's output.
+    fn update(&self);
+
+    /// Adds the element (and the logic that feeds it, as of the current cycle)
+    /// to `design`, for exporters.
+    fn describe(&self, state: &S, design: &mut Design);
+}
+
+/// An op whose type we've forgotten (state elements and simulators have to be
+/// able to hold ops of any type).
+trait Signal<S, T> {
+    fn eval(&self, state: &S, trace: &mut dyn Trace) -> T;
+
+    fn graph(&self, state: &S) -> OpGraph;
+}
+
+/// An op, producing its own output type.
+struct Next<T>(T);
+
+impl<S, T: Op<S>> Signal<S, T::Output> for Next<OpWrapper<T, S>> {
+    fn eval(&self, state: &S, trace: &mut dyn Trace) -> T::Output {
+        self.0.execute_with_trace(state, trace)
+    }
+
+    fn graph(&self, state: &S) -> OpGraph {
+        self.0.execute_with_graph(state).1
+    }
+}
+
+/// An op, producing `Bits`.
+struct AsBits<T>(T);
+
+impl<S, T: Op<S>> Signal<S, Bits> for AsBits<OpWrapper<T, S>> {
+    fn eval(&self, state: &S, trace: &mut dyn Trace) -> Bits {
+        self.0.execute_with_trace(state, trace).to_bits()
+    }
+
+    fn graph(&self, state: &S) -> OpGraph {
+        self.0.execute_with_graph(state).1
+    }
+}
+
+/// A one bit signal, as a `bool`.
+struct Asserted<T>(T);
+
+impl<S, T: Op<S>> Signal<S, bool> for Asserted<OpWrapper<T, S>>
+where
+    T::Output: Bit,
+{
+    fn eval(&self, state: &S, trace: &mut dyn Trace) -> bool {
+        self.0.execute_with_trace(state, trace).index() == 1
+    }
+
+    fn graph(&self, state: &S) -> OpGraph {
+        self.0.execute_with_graph(state).1
+    }
+}
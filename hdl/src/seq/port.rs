@@ -0,0 +1,144 @@
+//! Named inputs that are driven from outside of a design.
+
+use crate::bits::{Bits, FromBits, Value};
+use crate::ops::{Exit, Op, OpKind, OpWrapper, Param, Trace};
+use crate::wires::{BitCountType, Width, WireOfWidth};
+
+use alloc::rc::Rc;
+use core::cell::Cell;
+use core::fmt::{self, Debug, Display};
+
+/// Something that can be set from outside of a design, without knowing its
+/// type (i.e. by a [`Simulator`](struct.Simulator.html)).
+pub trait Drivable {
+    /// The name of the port.
+    fn name(&self) -> &'static str;
+
+    /// The value the port is currently being driven with.
+    fn value(&self) -> Bits;
+
+    /// Drives the port with `val`, which must be exactly as wide as the port.
+    fn drive_bits(&self, val: Bits);
+}
+
+/// A `B` bit wide input port.
+///
+/// Ports hold whatever they were last driven with. They're read in expressions
+/// with [`out`](#method.out).
+///
+/// Handles are cheap to clone and all refer to the same port.
+pub struct Input<const B: BitCountType>
+where
+    Width<{ B }>: WireOfWidth,
+{
+    name: &'static str,
+    value: Rc<Cell<<Width<{ B }> as WireOfWidth>::Wire>>,
+}
+
+impl<const B: BitCountType> Input<{ B }>
+where
+    Width<{ B }>: WireOfWidth,
+{
+    /// A port named `name` that's driven with `init` until it's told otherwise.
+    pub fn new(name: &'static str, init: <Width<{ B }> as WireOfWidth>::Wire) -> Self {
+        Self { name, value: Rc::new(Cell::new(init)) }
+    }
+
+    /// An op that produces the value the port is being driven with.
+    pub fn out<S>(&self) -> OpWrapper<InputPort<<Width<{ B }> as WireOfWidth>::Wire>, S> {
+        InputPort {
+            name: self.name,
+            value: self.value.clone(),
+        }
+        .into()
+    }
+
+    /// Drives the port with `val`.
+    pub fn drive(&self, val: <Width<{ B }> as WireOfWidth>::Wire) {
+        self.value.set(val)
+    }
+
+    /// The value the port is being driven with.
+    pub fn get(&self) -> <Width<{ B }> as WireOfWidth>::Wire {
+        self.value.get()
+    }
+}
+
+impl<const B: BitCountType> Drivable for Input<{ B }>
+where
+    Width<{ B }>: WireOfWidth,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn value(&self) -> Bits {
+        self.get().to_bits()
+    }
+
+    fn drive_bits(&self, val: Bits) {
+        assert_eq!(
+            val.width(), B,
+            "`{}` is {} bits wide; can't drive it with {} bits", self.name, B, val.width()
+        );
+
+        self.drive(FromBits::from_bits(val))
+    }
+}
+
+impl<const B: BitCountType> Clone for Input<{ B }>
+where
+    Width<{ B }>: WireOfWidth,
+{
+    fn clone(&self) -> Self {
+        Self { name: self.name, value: self.value.clone() }
+    }
+}
+
+impl<const B: BitCountType> Debug for Input<{ B }>
+where
+    Width<{ B }>: WireOfWidth,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Input")
+            .field("name", &self.name)
+            .field("value", &format_args!("{}", self.get()))
+            .finish()
+    }
+}
+
+/// Reads an [`Input`](struct.Input.html).
+///
+/// Shows up in metadata as the port's name and value, i.e. `<ld_pc=1'h1>`.
+#[derive(Clone)]
+pub struct InputPort<W> {
+    name: &'static str,
+    value: Rc<Cell<W>>,
+}
+
+impl<S, W: Copy + Display + Value> Op<S> for InputPort<W> {
+    type Output = W;
+    const OPERATION: OpKind = OpKind::Input;
+
+    fn execute(&self, _state: &S) -> W {
+        self.value.get()
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> W {
+        trace.enter(OpKind::Input, None);
+
+        let val = self.execute(state);
+        trace.exit(&Exit::new(OpKind::Input, &val).with_display(&val).with_param(Param::Name(self.name)));
+
+        val
+    }
+}
+
+impl<W: Copy + Display> Debug for InputPort<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InputPort")
+            .field("name", &self.name)
+            .field("value", &format_args!("{}", self.value.get()))
+            .finish()
+    }
+}
@@ -0,0 +1,384 @@
+//! A clocked register with an optional write enable and reset.
+
+use super::{Asserted, Clocked, Next, Signal};
+use crate::bits::{Bits, Value};
+use crate::export::{Design, RegisterDesc};
+use crate::ops::{Bit, Exit, InRun, Op, OpKind, OpWrapper, Param, Run, Trace};
+use crate::wires::{BitCountType, Width, WireOfWidth};
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use core::cell::{Cell, RefCell};
+use core::fmt::{self, Debug, Display};
+
+/// When a register's reset takes effect.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Reset {
+    /// On the next clock edge, like any other input.
+    Sync,
+    /// As soon as the reset signal is asserted, without waiting for the clock.
+    Async,
+}
+
+struct Inner<S, W> {
+    name: &'static str,
+    init: W,
+    /// Shared with every `RegisterOutput` for this register. Those don't hold
+    /// onto the rest of this struct so that a register whose next value
+    /// depends on its own output isn't a reference cycle.
+    value: Rc<Cell<W>>,
+    sampled: Cell<Option<W>>,
+    next: RefCell<Option<Box<dyn Signal<S, W>>>>,
+    enable: RefCell<Option<Box<dyn Signal<S, bool>>>>,
+    reset: RefCell<Option<(Box<dyn Signal<S, bool>>, Reset)>>,
+}
+
+/// A `B` bit wide register.
+///
+/// Registers start out holding their reset value. On every clock edge a
+/// register takes the value of its next-value input, unless:
+///   - its reset is asserted; the register goes back to its reset value.
+///     Asynchronous resets also do this when the register is
+///     [settled](trait.Clocked.html#tymethod.settle), without a clock edge.
+///   - it has a write enable and the enable isn't asserted; the register keeps
+///     its value.
+///
+/// A register without a next-value input just holds its value.
+///
+/// The register's value is read in expressions with [`out`](#method.out). Since
+/// a register's inputs are usually expressions that involve registers
+/// (including, often, the register itself) the inputs are connected after the
+/// register is made:
+///
+/// ```rust,ignore
+/// let pc: Register<State, 16> = Register::new("pc", w!(16#0x3000));
+/// let two: OpWrapper<Imm<Wire<{ 16 }, { 2 }>>, State> = w!(16#2).into();
+///
+/// pc.next(pc.out() + two).enable_when(ld_pc);
+/// ```
+///
+/// Handles are cheap to clone and all refer to the same register.
+pub struct Register<S, const B: BitCountType>
+where
+    Width<{ B }>: WireOfWidth,
+{
+    inner: Rc<Inner<S, <Width<{ B }> as WireOfWidth>::Wire>>,
+}
+
+impl<S, const B: BitCountType> Register<S, { B }>
+where
+    Width<{ B }>: WireOfWidth,
+{
+    /// A register named `name` that resets to (and starts out as) `init`.
+    pub fn new(name: &'static str, init: <Width<{ B }> as WireOfWidth>::Wire) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                name,
+                init,
+                value: Rc::new(Cell::new(init)),
+                sampled: Cell::new(None),
+                next: RefCell::new(None),
+                enable: RefCell::new(None),
+                reset: RefCell::new(None),
+            }),
+        }
+    }
+
+    /// An op that produces the register's current value.
+    pub fn out(&self) -> OpWrapper<RegisterOutput<<Width<{ B }> as WireOfWidth>::Wire>, S> {
+        RegisterOutput {
+            name: self.inner.name,
+            value: self.inner.value.clone(),
+        }
+        .into()
+    }
+
+    /// Connects the register's next-value input; replaces whatever was
+    /// connected before.
+    pub fn next<T>(&self, op: OpWrapper<T, S>) -> &Self
+    where
+        T: Op<S, Output = <Width<{ B }> as WireOfWidth>::Wire> + 'static,
+        S: 'static,
+    {
+        *self.inner.next.borrow_mut() = Some(Box::new(Next(op)));
+        self
+    }
+
+    /// Only lets the register change on clock edges where `op` is asserted.
+    pub fn enable_when<T>(&self, op: OpWrapper<T, S>) -> &Self
+    where
+        T: Op<S> + 'static,
+        T::Output: Bit,
+        S: 'static,
+    {
+        *self.inner.enable.borrow_mut() = Some(Box::new(Asserted(op)));
+        self
+    }
+
+    /// Sends the register back to its reset value when `op` is asserted.
+    pub fn reset_when<T>(&self, op: OpWrapper<T, S>, kind: Reset) -> &Self
+    where
+        T: Op<S> + 'static,
+        T::Output: Bit,
+        S: 'static,
+    {
+        *self.inner.reset.borrow_mut() = Some((Box::new(Asserted(op)), kind));
+        self
+    }
+
+    /// The register's current value.
+    pub fn get(&self) -> <Width<{ B }> as WireOfWidth>::Wire {
+        self.inner.value.get()
+    }
+
+    /// Overwrites the register's current value, bypassing its inputs.
+    pub fn set(&self, val: <Width<{ B }> as WireOfWidth>::Wire) {
+        self.inner.value.set(val)
+    }
+
+    /// A single clock cycle for just this register.
+    ///
+    /// Registers that read each other should be clocked together (sample all
+    /// of them, then update all of them); see the [module docs](index.html).
+    pub fn clock(&self, state: &S) {
+        self.settle(state);
+        self.sample(state, &mut InRun::new(&mut (), &mut Run::new()));
+        self.update();
+    }
+
+    fn reset_asserted(&self, state: &S, trace: &mut dyn Trace) -> Option<Reset> {
+        match &*self.inner.reset.borrow() {
+            Some((sig, kind)) if sig.eval(state, trace) => Some(*kind),
+            _ => None,
+        }
+    }
+}
+
+impl<S, const B: BitCountType> Clocked<S> for Register<S, { B }>
+where
+    Width<{ B }>: WireOfWidth,
+{
+    fn name(&self) -> &'static str {
+        self.inner.name
+    }
+
+    fn value(&self) -> Option<Bits> {
+        Some(self.get().to_bits())
+    }
+
+    fn settle(&self, state: &S) {
+        // Resetting changes what other elements' logic sees, so every element
+        // settles in a run of its own.
+        if let Some(Reset::Async) = self.reset_asserted(state, &mut ()) {
+            self.inner.value.set(self.inner.init);
+        }
+    }
+
+    fn sample(&self, state: &S, trace: &mut dyn Trace) {
+        let inner = &*self.inner;
+
+        let reset = self.reset_asserted(state, trace).is_some();
+        let enabled = match &*inner.enable.borrow() {
+            Some(en) => en.eval(state, trace),
+            None => true,
+        };
+        let next = match &*inner.next.borrow() {
+            Some(next) => next.eval(state, trace),
+            None => inner.value.get(),
+        };
+
+        let val = if reset {
+            inner.init
+        } else if enabled {
+            next
+        } else {
+            inner.value.get()
+        };
+
+        inner.sampled.set(Some(val));
+    }
+
+    fn update(&self) {
+        if let Some(val) = self.inner.sampled.take() {
+            self.inner.value.set(val);
+        }
+    }
+
+    fn describe(&self, state: &S, design: &mut Design) {
+        let inner = &*self.inner;
+
+        design.registers.push(RegisterDesc {
+            name: inner.name,
+            init: inner.init.to_bits(),
+            next: inner.next.borrow().as_ref().map(|n| n.graph(state)),
+            enable: inner.enable.borrow().as_ref().map(|e| e.graph(state)),
+            reset: inner.reset.borrow().as_ref().map(|(r, kind)| (r.graph(state), *kind)),
+        });
+    }
+}
+
+impl<S, const B: BitCountType> Clone for Register<S, { B }>
+where
+    Width<{ B }>: WireOfWidth,
+{
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl<S, const B: BitCountType> Debug for Register<S, { B }>
+where
+    Width<{ B }>: WireOfWidth,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Register")
+            .field("name", &self.inner.name)
+            .field("value", &format_args!("{}", self.get()))
+            .field("init", &format_args!("{}", self.inner.init))
+            .finish()
+    }
+}
+
+/// Reads a [`Register`](struct.Register.html).
+///
+/// Shows up in metadata as the register's name and value, i.e. `<pc=16'h3000>`.
+#[derive(Clone)]
+pub struct RegisterOutput<W> {
+    name: &'static str,
+    value: Rc<Cell<W>>,
+}
+
+impl<S, W: Copy + Display + Value> Op<S> for RegisterOutput<W> {
+    type Output = W;
+    const OPERATION: OpKind = OpKind::Register;
+
+    fn execute(&self, _state: &S) -> W {
+        self.value.get()
+    }
+
+    fn execute_with_trace(&self, state: &S, trace: &mut dyn Trace) -> W {
+        trace.enter(OpKind::Register, None);
+
+        let val = self.execute(state);
+        trace.exit(&Exit::new(OpKind::Register, &val).with_display(&val).with_param(Param::Name(self.name)));
+
+        val
+    }
+}
+
+impl<W: Copy + Display> Debug for RegisterOutput<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegisterOutput")
+            .field("name", &self.name)
+            .field("value", &format_args!("{}", self.value.get()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::{Imm, RegisterInput};
+    use crate::state::Registers;
+    use crate::w;
+    use crate::wires::Wire;
+
+    type W8 = Wire<{ 8 }, { 1 }>;
+
+    /// r0 is the write enable, r1 is the reset.
+    struct Ctl(Cell<[bool; 2]>);
+
+    impl Registers for Ctl {
+        type Word = bool;
+
+        fn read_reg(&self, num: u8) -> bool {
+            self.0.get()[num as usize]
+        }
+
+        fn write_reg(&self, num: u8, word: bool) {
+            let mut regs = self.0.get();
+            regs[num as usize] = word;
+            self.0.set(regs);
+        }
+    }
+
+    fn counter(reset: Reset) -> Register<Ctl, { 8 }> {
+        let r: Register<Ctl, { 8 }> = Register::new("cnt", w!(8#0x10));
+        let one: OpWrapper<Imm<W8>, Ctl> = w!(8#1).into();
+
+        let _ = r
+            .next(r.out() + one)
+            .enable_when(RegisterInput::new(0))
+            .reset_when(RegisterInput::new(1), reset);
+        r
+    }
+
+    #[test]
+    fn counts() {
+        let r: Register<(), { 8 }> = Register::new("pc", w!(8#0xFE));
+        let one: OpWrapper<Imm<W8>> = w!(8#1).into();
+        let _ = r.next(r.out() + one);
+
+        r.clock(&());
+        assert_eq!(r.out().execute_with_metadata(&()).0, "<pc=8'hff>");
+
+        r.clock(&());
+        assert_eq!(u8::from(r.get()), 0);
+    }
+
+    #[test]
+    fn enable() {
+        let ctl = Ctl(Cell::new([true, false]));
+        let r = counter(Reset::Sync);
+
+        r.clock(&ctl);
+        assert_eq!(u8::from(r.get()), 0x11);
+
+        ctl.write_reg(0, false);
+        r.clock(&ctl);
+        assert_eq!(u8::from(r.get()), 0x11);
+    }
+
+    #[test]
+    fn sync_reset() {
+        let ctl = Ctl(Cell::new([true, false]));
+        let r = counter(Reset::Sync);
+        r.clock(&ctl);
+
+        // Resets win over the enable and wait for the clock.
+        ctl.write_reg(0, false);
+        ctl.write_reg(1, true);
+        r.settle(&ctl);
+        assert_eq!(u8::from(r.get()), 0x11);
+
+        r.clock(&ctl);
+        assert_eq!(u8::from(r.get()), 0x10);
+    }
+
+    #[test]
+    fn async_reset() {
+        let ctl = Ctl(Cell::new([true, false]));
+        let r = counter(Reset::Async);
+        r.clock(&ctl);
+
+        ctl.write_reg(1, true);
+        r.settle(&ctl);
+        assert_eq!(u8::from(r.get()), 0x10);
+    }
+
+    #[test]
+    fn two_phase() {
+        let a: Register<(), { 8 }> = Register::new("a", w!(8#1));
+        let b: Register<(), { 8 }> = Register::new("b", w!(8#2));
+        let _ = a.next(b.out());
+        let _ = b.next(a.out());
+
+        let mut run = Run::new();
+        a.sample(&(), &mut InRun::new(&mut (), &mut run));
+        b.sample(&(), &mut InRun::new(&mut (), &mut run));
+        a.update();
+        b.update();
+
+        assert_eq!((u8::from(a.get()), u8::from(b.get())), (2, 1));
+    }
+}
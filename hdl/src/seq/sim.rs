@@ -0,0 +1,439 @@
+//! Clocking a whole design, cycle by cycle.
+
+use super::vcd::{Kind, Vcd};
+use super::{AsBits, Clocked, Drivable, Signal};
+use crate::bits::{Bits, Value};
+use crate::export::{Design, PortDesc};
+use crate::ops::{Exit, InRun, Op, OpKind, OpWrapper, Run, Trace};
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug};
+
+/// Owns a design (its state, state elements, and inputs) and runs it.
+///
+/// Each [`step`](#method.step) is one clock cycle: every state element samples
+/// its inputs and then every state element updates. Asynchronous resets are
+/// applied before the clock edge, after it, and whenever the simulator is
+/// asked for a value (since inputs may have been driven in the meantime).
+///
+/// Combinational logic is evaluated on demand; elements evaluate their inputs
+/// when they're sampled (all in one [`Run`](../ops/struct.Run.html), so
+/// [shared](../ops/struct.Shared.html) logic is only evaluated once per cycle),
+/// and any other expression can be evaluated against the current cycle with
+/// [`eval`](#method.eval) or given a name with [`probe`](#method.probe).
+///
+/// Runs can be recorded as a waveform; see [`start_vcd`](#method.start_vcd).
+pub struct Simulator<S> {
+    state: S,
+    cycle: u64,
+    elements: Vec<Box<dyn Clocked<S>>>,
+    inputs: Vec<Box<dyn Drivable>>,
+    probes: Vec<(&'static str, Box<dyn Signal<S, Bits>>)>,
+    vcd: Option<Vcd>,
+}
+
+impl<S> Simulator<S> {
+    /// A simulator for a design that reads from `state`. The design's state
+    /// elements and inputs have to be added before they're of any use.
+    pub fn new(state: S) -> Self {
+        Self {
+            state,
+            cycle: 0,
+            elements: Vec::new(),
+            inputs: Vec::new(),
+            probes: Vec::new(),
+            vcd: None,
+        }
+    }
+
+    /// Adds a state element (i.e. a [`Register`](struct.Register.html)) to the
+    /// design, to be clocked along with the rest of the design's elements.
+    pub fn add_clocked(&mut self, element: impl Clocked<S> + 'static) -> &mut Self {
+        self.elements.push(Box::new(element));
+        self
+    }
+
+    /// Adds an input port to the design so that it can be driven by name.
+    pub fn add_input(&mut self, input: impl Drivable + 'static) -> &mut Self {
+        self.inputs.push(Box::new(input));
+        self
+    }
+
+    /// Names an expression so that its value can be looked up with
+    /// [`value`](#method.value). Probes are the design's outputs when it's
+    /// [exported](#method.design).
+    pub fn probe<T: Op<S> + 'static>(&mut self, name: &'static str, op: OpWrapper<T, S>) -> &mut Self
+    where
+        S: 'static,
+    {
+        self.probes.push((name, Box::new(AsBits(op))));
+        self
+    }
+
+    /// The state the design reads from.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// The number of clock cycles that have happened so far.
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    /// Drives the input named `name` with `val`.
+    ///
+    /// Panics if there's no such input or if `val` isn't as wide as the input.
+    pub fn drive(&self, name: &str, val: impl Value) {
+        match self.inputs.iter().find(|i| i.name() == name) {
+            Some(input) => input.drive_bits(val.to_bits()),
+            None => panic!("there's no input named `{}`", name),
+        }
+    }
+
+    /// The current value of the input, register, or probe named `name` (in
+    /// that order), if there is one. Registers include the ones state elements
+    /// clock along with themselves (i.e. registered RAM reads); see
+    /// [`Clocked::signals`](trait.Clocked.html#method.signals).
+    pub fn value(&self, name: &str) -> Option<Bits> {
+        self.settle();
+
+        let mut registers = self.elements.iter().flat_map(|e| e.signals());
+
+        if let Some(input) = self.inputs.iter().find(|i| i.name() == name) {
+            Some(input.value())
+        } else if let Some((_, val)) = registers.find(|(n, _)| *n == name) {
+            Some(val)
+        } else {
+            let probe = self.probes.iter().find(|(n, _)| *n == name);
+            probe.map(|(_, p)| p.eval(&self.state, &mut ()))
+        }
+    }
+
+    /// Describes the design for exporters. Probes become the design's outputs.
+    ///
+    /// The graphs in the description are recorded by running the design's
+    /// logic against the current cycle; only the values in them depend on
+    /// when this is called.
+    pub fn design(&self) -> Design {
+        self.settle();
+
+        let mut design = Design::new();
+        for i in self.inputs.iter() {
+            design.inputs.push(PortDesc { name: i.name(), width: i.value().width() });
+        }
+        for (name, p) in self.probes.iter() {
+            design.outputs.push((*name, p.graph(&self.state)));
+        }
+        for e in self.elements.iter() {
+            e.describe(&self.state, &mut design);
+        }
+
+        design
+    }
+
+    /// Evaluates `op` against the current cycle.
+    pub fn eval<T: Op<S>>(&self, op: &OpWrapper<T, S>) -> T::Output {
+        self.settle();
+        op.execute(&self.state)
+    }
+
+    /// Runs a single clock cycle.
+    pub fn step(&mut self) {
+        // Inputs may have been driven since the last cycle.
+        self.settle();
+        self.sample();
+
+        for e in self.elements.iter() {
+            e.update();
+        }
+
+        self.settle();
+        self.cycle += 1;
+    }
+
+    /// Runs `cycles` clock cycles.
+    pub fn run(&mut self, cycles: u64) {
+        for _ in 0..cycles {
+            self.step();
+        }
+    }
+
+    /// Steps until `done` is true, checking before every cycle, and returns the
+    /// cycle `done` became true on.
+    ///
+    /// Gives up (and returns `None`) after `limit` cycles.
+    pub fn run_until(&mut self, limit: u64, mut done: impl FnMut(&Self) -> bool) -> Option<u64> {
+        for _ in 0..limit {
+            if done(self) {
+                return Some(self.cycle);
+            }
+
+            self.step();
+        }
+
+        if done(self) { Some(self.cycle) } else { None }
+    }
+
+    /// Starts recording a Value Change Dump of the run, from the current cycle
+    /// on (one cycle is one time unit). Any recording that's in progress is
+    /// thrown away.
+    ///
+    /// Every input, register, probe, and labeled op in the design becomes a
+    /// variable. Names are split on dots into scopes (so an op labeled
+    /// `alu.sum` shows up as `sum` within `alu`) and everything is within a top
+    /// level scope named `module`.
+    ///
+    /// Each cycle is recorded when it ends (i.e. by [`step`](#method.step)),
+    /// from the same evaluation of the design's logic that clocks it.
+    pub fn start_vcd(&mut self, module: &str) {
+        self.settle();
+        self.vcd = Some(Vcd::new(module, self.cycle, &self.sample_all()));
+    }
+
+    /// The Value Change Dump recorded so far (up to the end of the last cycle),
+    /// if one was started.
+    pub fn vcd(&self) -> Option<&str> {
+        self.vcd.as_ref().map(|v| v.text())
+    }
+
+    /// Stops recording and returns the Value Change Dump, if one was started.
+    /// The current cycle is recorded first and the dump ends at its time.
+    pub fn finish_vcd(&mut self) -> Option<String> {
+        if self.vcd.is_some() {
+            self.settle();
+            self.sample();
+        }
+
+        let cycle = self.cycle;
+        self.vcd.take().map(|v| v.finish(cycle))
+    }
+
+    /// Samples every element, in one run. If a Value Change Dump is being
+    /// recorded, the current cycle is recorded from that run too.
+    fn sample(&mut self) {
+        if self.vcd.is_none() {
+            let mut run = Run::new();
+            for e in self.elements.iter() {
+                e.sample(&self.state, &mut InRun::new(&mut (), &mut run));
+            }
+
+            return;
+        }
+
+        let signals = self.sample_all();
+        let cycle = self.cycle;
+        if let Some(vcd) = self.vcd.as_mut() {
+            vcd.record(cycle, &signals);
+        }
+    }
+
+    /// Samples every element and evaluates every probe, all in one run, and
+    /// returns the name and value of everything in the design that has both.
+    fn sample_all(&self) -> Vec<(&'static str, Kind, Bits)> {
+        let (mut run, mut labels) = (Run::new(), Labels(Vec::new()));
+        let mut trace = InRun::new(&mut labels, &mut run);
+
+        for e in self.elements.iter() {
+            e.sample(&self.state, &mut trace);
+        }
+        let probes: Vec<(&'static str, Bits)> =
+            self.probes.iter().map(|(name, p)| (*name, p.eval(&self.state, &mut trace))).collect();
+
+        let mut signals: Vec<(&'static str, Kind, Bits)> = Vec::new();
+        let mut add = |name: &'static str, kind: Kind, val: Bits| {
+            if !signals.iter().any(|(n, _, _)| *n == name) {
+                signals.push((name, kind, val));
+            }
+        };
+
+        for i in self.inputs.iter() {
+            add(i.name(), Kind::Wire, i.value());
+        }
+        for (name, val) in self.elements.iter().flat_map(|e| e.signals()) {
+            add(name, Kind::Reg, val);
+        }
+        for (name, val) in probes {
+            add(name, Kind::Wire, val);
+        }
+        for (label, val) in labels.0 {
+            add(label, Kind::Wire, val);
+        }
+
+        signals
+    }
+
+    fn settle(&self) {
+        for e in self.elements.iter() {
+            e.settle(&self.state);
+        }
+    }
+}
+
+/// Collects the value of every labeled op that's evaluated (the first value,
+/// if a label shows up more than once).
+struct Labels(Vec<(&'static str, Bits)>);
+
+impl Labels {
+    fn add(&mut self, node: &Exit<'_>) {
+        if let Some(label) = node.label {
+            if !self.0.iter().any(|(l, _)| *l == label) {
+                self.0.push((label, node.value));
+            }
+        }
+    }
+}
+
+impl Trace for Labels {
+    fn enter(&mut self, _kind: OpKind, _label: Option<&'static str>) {}
+
+    fn exit(&mut self, node: &Exit<'_>) {
+        self.add(node);
+    }
+
+    fn share(&mut self, _key: usize) {}
+
+    fn reuse(&mut self, _key: usize, node: &Exit<'_>) {
+        self.add(node);
+    }
+}
+
+impl<S: Debug> Debug for Simulator<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let elements: Vec<&str> = self.elements.iter().map(|e| e.name()).collect();
+        let inputs: Vec<&str> = self.inputs.iter().map(|i| i.name()).collect();
+        let probes: Vec<&str> = self.probes.iter().map(|(n, _)| *n).collect();
+
+        f.debug_struct("Simulator")
+            .field("state", &self.state)
+            .field("cycle", &self.cycle)
+            .field("elements", &elements)
+            .field("inputs", &inputs)
+            .field("probes", &probes)
+            .field("recording", &self.vcd.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::Imm;
+    use crate::seq::{Input, Register, Reset};
+    use crate::w;
+    use crate::wires::Wire;
+
+    type W8 = Wire<{ 8 }, { 1 }>;
+
+    /// A counter that counts by `inc` while `en` is driven, with an
+    /// asynchronous reset.
+    fn counter() -> Simulator<()> {
+        let en: Input<{ 1 }> = Input::new("en", w!(1#1));
+        let rst: Input<{ 1 }> = Input::new("rst", w!(1#0));
+        let inc: Input<{ 8 }> = Input::new("inc", w!(8#1));
+        let cnt: Register<(), { 8 }> = Register::new("cnt", w!(8#0));
+
+        let _ = cnt
+            .next(cnt.out() + inc.out())
+            .enable_when(en.out())
+            .reset_when(rst.out(), Reset::Async);
+
+        let zero: OpWrapper<Imm<W8>> = w!(8#0).into();
+
+        let mut sim = Simulator::new(());
+        let _ = sim
+            .add_clocked(cnt.clone())
+            .add_input(en)
+            .add_input(rst)
+            .add_input(inc)
+            .probe("wrapped", cnt.out().eq(zero));
+        sim
+    }
+
+    #[test]
+    fn steps() {
+        let mut sim = counter();
+        sim.run(3);
+
+        assert_eq!(sim.cycle(), 3);
+        assert_eq!(sim.value("cnt"), Some(Bits::new(8, 3)));
+
+        sim.drive("inc", w!(8#0x10));
+        sim.step();
+        assert_eq!(sim.value("cnt"), Some(Bits::new(8, 0x13)));
+
+        sim.drive("en", w!(1#0));
+        sim.run(2);
+        assert_eq!(sim.value("cnt"), Some(Bits::new(8, 0x13)));
+        assert_eq!(sim.cycle(), 6);
+    }
+
+    #[test]
+    fn resets_between_cycles() {
+        let mut sim = counter();
+        sim.run(3);
+
+        sim.drive("rst", w!(1#1));
+        assert_eq!(sim.value("cnt"), Some(Bits::new(8, 0)));
+        assert_eq!(sim.value("wrapped"), Some(Bits::new(1, 1)));
+    }
+
+    #[test]
+    fn runs_until() {
+        let mut sim = counter();
+        sim.drive("inc", w!(8#0x40));
+
+        let wrapped = |s: &Simulator<()>| s.cycle() > 0 && s.value("wrapped") == Some(Bits::new(1, 1));
+
+        assert_eq!(sim.run_until(10, wrapped), Some(4));
+        assert_eq!(sim.run_until(3, |s| s.value("cnt") == Some(Bits::new(8, 0xFF))), None);
+        assert_eq!(sim.cycle(), 7);
+    }
+
+    #[test]
+    fn shared_logic_once_per_cycle() {
+        use crate::ops::RegisterInput;
+        use crate::state::Registers;
+        use core::cell::Cell;
+
+        /// Counts register file reads.
+        #[derive(Debug, Default)]
+        struct Reads(Cell<usize>);
+
+        impl Registers for Reads {
+            type Word = W8;
+
+            fn read_reg(&self, _num: u8) -> W8 {
+                self.0.set(self.0.get() + 1);
+                w!(8#1)
+            }
+
+            fn write_reg(&self, _num: u8, _word: W8) {}
+        }
+
+        // Both registers read the same (shared) register file read:
+        let r = RegisterInput::new::<Reads>(0).share();
+        let a: Register<Reads, { 8 }> = Register::new("a", w!(8#0));
+        let b: Register<Reads, { 8 }> = Register::new("b", w!(8#0));
+        let _ = a.next(r.clone() + r.clone());
+        let _ = b.next(r);
+
+        let mut sim = Simulator::new(Reads::default());
+        let _ = sim.add_clocked(a).add_clocked(b);
+
+        sim.step();
+        assert_eq!(sim.state().0.get(), 1);
+
+        // Recording a waveform doesn't evaluate anything again.
+        sim.start_vcd("top");
+        sim.step();
+        assert_eq!(sim.state().0.get(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn drive_width() {
+        counter().drive("inc", w!(4#1));
+    }
+}
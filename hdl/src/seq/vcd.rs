@@ -0,0 +1,254 @@
+//! Value Change Dump (IEEE 1364) output, for waveform viewers like GTKWave.
+
+use crate::bits::Bits;
+use crate::export::ident;
+use crate::wires::BitCountType;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// What a variable holds.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum Kind {
+    /// A combinational value: a port, probe, or labeled op.
+    Wire,
+    /// The value of a state element.
+    Reg,
+}
+
+struct Var {
+    /// The full (dotted) name.
+    name: String,
+    kind: Kind,
+    width: BitCountType,
+    id: String,
+    last: Bits,
+}
+
+/// The short identifier VCD files use to refer to the `n`th variable: base 94,
+/// using the printable ASCII characters.
+fn id(mut n: usize) -> String {
+    let mut s = String::new();
+
+    loop {
+        s.push((b'!' + (n % 94) as u8) as char);
+        n /= 94;
+
+        if n == 0 {
+            return s;
+        }
+        n -= 1;
+    }
+}
+
+fn value(val: Bits, id: &str) -> String {
+    if val.width() == 1 {
+        format!("{}{}\n", val.val(), id)
+    } else {
+        format!("b{:b} {}\n", val.val(), id)
+    }
+}
+
+/// Writes the `$var`s in `vars` (whose names have already been split on dots),
+/// with a nested `$scope` for every group of names that share a prefix.
+fn scope(out: &mut String, vars: &[(Vec<&str>, &Var)], depth: usize) {
+    let mut groups: Vec<&str> = Vec::new();
+
+    for (path, var) in vars.iter() {
+        if path.len() == depth + 1 {
+            let kind = match var.kind {
+                Kind::Wire => "wire",
+                Kind::Reg => "reg",
+            };
+
+            out.push_str(&format!(
+                "$var {} {} {} {} $end\n",
+                kind, var.width, var.id, ident(path[depth])
+            ));
+        } else if !groups.contains(&path[depth]) {
+            groups.push(path[depth]);
+        }
+    }
+
+    for g in groups {
+        let inner: Vec<(Vec<&str>, &Var)> =
+            vars.iter().filter(|(p, _)| p.len() > depth + 1 && p[depth] == g).cloned().collect();
+
+        out.push_str(&format!("$scope module {} $end\n", ident(g)));
+        scope(out, &inner, depth + 1);
+        out.push_str("$upscope $end\n");
+    }
+}
+
+/// A VCD file that's being written.
+///
+/// Variables are fixed when the file is started; every later call to
+/// [`record`](#method.record) only writes the variables that changed.
+pub(super) struct Vcd {
+    vars: Vec<Var>,
+    out: String,
+    time: u64,
+}
+
+impl Vcd {
+    /// Starts a file for `signals` (names, kinds, and current values), at
+    /// `time`, with everything inside a top level scope named `module`.
+    pub(super) fn new(module: &str, time: u64, signals: &[(&str, Kind, Bits)]) -> Self {
+        let vars: Vec<Var> = signals
+            .iter()
+            .enumerate()
+            .map(|(i, (name, kind, val))| Var {
+                name: String::from(*name),
+                kind: *kind,
+                width: val.width(),
+                id: id(i),
+                last: *val,
+            })
+            .collect();
+
+        let mut out = String::from("$timescale 1ns $end\n");
+        out.push_str(&format!("$scope module {} $end\n", ident(module)));
+
+        let paths: Vec<(Vec<&str>, &Var)> =
+            vars.iter().map(|v| (v.name.split('.').collect(), v)).collect();
+        scope(&mut out, &paths, 0);
+
+        out.push_str("$upscope $end\n$enddefinitions $end\n");
+        out.push_str(&format!("#{}\n$dumpvars\n", time));
+        for v in vars.iter() {
+            out.push_str(&value(v.last, &v.id));
+        }
+        out.push_str("$end\n");
+
+        Self { vars, out, time }
+    }
+
+    /// Writes whatever's changed in `signals` since the last call, at `time`.
+    /// Signals that weren't around when the file was started are ignored.
+    pub(super) fn record(&mut self, time: u64, signals: &[(&str, Kind, Bits)]) {
+        for (name, _, val) in signals.iter() {
+            let var = match self.vars.iter_mut().find(|v| v.name == *name) {
+                Some(var) if var.last != *val => var,
+                _ => continue,
+            };
+
+            if time != self.time {
+                self.out.push_str(&format!("#{}\n", time));
+                self.time = time;
+            }
+
+            self.out.push_str(&value(*val, &var.id));
+            var.last = *val;
+        }
+    }
+
+    /// Everything that's been written so far.
+    pub(super) fn text(&self) -> &str {
+        &self.out
+    }
+
+    /// Ends the file at `time` (so that viewers show the last values for as
+    /// long as they lasted) and returns it.
+    pub(super) fn finish(mut self, time: u64) -> String {
+        if time != self.time {
+            self.out.push_str(&format!("#{}\n", time));
+        }
+
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::{Imm, OpWrapper};
+    use crate::seq::{Input, Ram, Register, Simulator};
+    use crate::w;
+    use crate::wires::Wire;
+
+    #[test]
+    fn ids() {
+        assert_eq!(id(0), "!");
+        assert_eq!(id(93), "~");
+        assert_eq!(id(94), "!!");
+        assert_eq!(id(95), "\"!");
+    }
+
+    #[test]
+    fn counter() {
+        let en: Input<{ 1 }> = Input::new("en", w!(1#1));
+        let cnt: Register<(), { 4 }> = Register::new("cnt", w!(4#0));
+        let one: OpWrapper<Imm<Wire<{ 4 }, { 1 }>>> = w!(4#1).into();
+        let _ = cnt.next((cnt.out() + one).label("alu.sum")).enable_when(en.out());
+
+        let mut sim = Simulator::new(());
+        let _ = sim.add_clocked(cnt.clone()).add_input(en);
+
+        sim.start_vcd("top");
+        sim.step();
+        sim.drive("en", w!(1#0));
+        sim.run(2);
+
+        assert_eq!(
+            sim.finish_vcd().unwrap(),
+            "$timescale 1ns $end\n\
+            $scope module top $end\n\
+            $var wire 1 ! en $end\n\
+            $var reg 4 \" cnt $end\n\
+            $scope module alu $end\n\
+            $var wire 4 # sum $end\n\
+            $upscope $end\n\
+            $upscope $end\n\
+            $enddefinitions $end\n\
+            #0\n\
+            $dumpvars\n\
+            1!\n\
+            b0 \"\n\
+            b1 #\n\
+            $end\n\
+            #1\n\
+            0!\n\
+            b1 \"\n\
+            b10 #\n\
+            #3\n"
+        );
+        assert_eq!(sim.vcd(), None);
+    }
+
+    #[test]
+    fn registered_reads() {
+        let addr: Input<{ 2 }> = Input::new("addr", w!(2#1));
+        let data: Input<{ 4 }> = Input::new("d", w!(4#5));
+        let we: Input<{ 1 }> = Input::new("we", w!(1#1));
+        let ram: Ram<(), { 2 }, { 4 }> = Ram::new("mem");
+        let _ = ram.write_port(addr.out(), data.out(), we.out());
+        let _ = ram.registered_read("q", addr.out());
+
+        let mut sim = Simulator::new(());
+        let _ = sim.add_clocked(ram).add_input(addr).add_input(data).add_input(we);
+
+        sim.start_vcd("top");
+        sim.run(2);
+
+        assert_eq!(
+            sim.finish_vcd().unwrap(),
+            "$timescale 1ns $end\n\
+            $scope module top $end\n\
+            $var wire 2 ! addr $end\n\
+            $var wire 4 \" d $end\n\
+            $var wire 1 # we $end\n\
+            $var reg 4 $ q $end\n\
+            $upscope $end\n\
+            $enddefinitions $end\n\
+            #0\n\
+            $dumpvars\n\
+            b1 !\n\
+            b101 \"\n\
+            1#\n\
+            b0 $\n\
+            $end\n\
+            #2\n\
+            b101 $\n"
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! The machine state that expressions can read from.
+//!
+//! Expressions (see [`ops`](../ops/index.html)) are executed against a context;
+//! these traits describe what a context can offer. Most contexts will want
+//! interior mutability since expressions only ever get a shared reference.
+
+/// A register file.
+pub trait Registers {
+    /// What a single register holds.
+    type Word;
+
+    /// Returns the value of register `num`.
+    fn read_reg(&self, num: u8) -> Self::Word;
+
+    /// Sets register `num` to `word`.
+    fn write_reg(&self, num: u8, word: Self::Word);
+}
+
+/// An addressable memory.
+pub trait Memory {
+    /// The type of memory addresses.
+    type Addr;
+    /// What a single memory location holds.
+    type Word;
+
+    /// Returns the value at `addr`.
+    fn read_mem(&self, addr: Self::Addr) -> Self::Word;
+
+    /// Sets the value at `addr` to `word`.
+    fn write_mem(&self, addr: Self::Addr, word: Self::Word);
+}
+
+/// Machine state: registers _and_ memory.
+pub trait Ctx: Registers + Memory {}
+impl<S: Registers + Memory> Ctx for S {}
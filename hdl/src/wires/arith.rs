@@ -0,0 +1,151 @@
+//! Arithmetic on wires.
+//!
+//! Wires are meant to behave like buses in actual hardware so everything here
+//! is fixed width: results that don't fit in the wire are truncated (i.e. all
+//! operations wrap).
+
+use super::{BitCountType, Wire};
+
+use core::ops::{Add, BitAnd, BitOr, BitXor, Not, Sub};
+
+impl<const B: BitCountType, const S: usize> Wire<{ B }, { S }> {
+    /// Zeroes the bits in the last byte that are past the end of the wire.
+    ///
+    /// Byte-wise operations don't know where the wire actually ends so they
+    /// should call this on their results.
+    #[inline]
+    pub(crate) fn truncate(&mut self) -> &mut Self {
+        if B % 8 != 0 {
+            self.repr[S - 1] &= (1u8 << (B % 8)) - 1;
+        }
+
+        self
+    }
+}
+
+impl<const B: BitCountType, const S: usize> Add for Wire<{ B }, { S }> {
+    type Output = Self;
+
+    /// Wrapping addition; carries propagate from the least significant byte
+    /// upwards and the final carry (if there is one) is dropped.
+    fn add(self, rhs: Self) -> Self {
+        let mut res = Self::new();
+        let mut carry = false;
+
+        for i in 0..S {
+            let (sum, c1) = self.repr[i].overflowing_add(rhs.repr[i]);
+            let (sum, c2) = sum.overflowing_add(carry as u8);
+
+            res.repr[i] = sum;
+            carry = c1 || c2;
+        }
+
+        *res.truncate()
+    }
+}
+
+impl<const B: BitCountType, const S: usize> Sub for Wire<{ B }, { S }> {
+    type Output = Self;
+
+    /// Wrapping subtraction; borrows propagate from the least significant byte
+    /// upwards and the final borrow (if there is one) is dropped.
+    fn sub(self, rhs: Self) -> Self {
+        let mut res = Self::new();
+        let mut borrow = false;
+
+        for i in 0..S {
+            let (diff, b1) = self.repr[i].overflowing_sub(rhs.repr[i]);
+            let (diff, b2) = diff.overflowing_sub(borrow as u8);
+
+            res.repr[i] = diff;
+            borrow = b1 || b2;
+        }
+
+        *res.truncate()
+    }
+}
+
+macro_rules! bitwise_impl {
+    ($trait:ident, $method:ident, $op:tt) => {
+        impl<const B: BitCountType, const S: usize> $trait for Wire<{ B }, { S }> {
+            type Output = Self;
+
+            fn $method(self, rhs: Self) -> Self {
+                let mut res = Self::new();
+
+                for i in 0..S {
+                    res.repr[i] = self.repr[i] $op rhs.repr[i];
+                }
+
+                res
+            }
+        }
+    };
+}
+
+bitwise_impl!(BitAnd, bitand, &);
+bitwise_impl!(BitOr, bitor, |);
+bitwise_impl!(BitXor, bitxor, ^);
+
+impl<const B: BitCountType, const S: usize> Not for Wire<{ B }, { S }> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        let mut res = Self::new();
+
+        for i in 0..S {
+            res.repr[i] = !self.repr[i];
+        }
+
+        // Unlike the other bitwise operations, this one turns on bits that
+        // aren't part of the wire:
+        *res.truncate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::w;
+
+    #[test]
+    fn add() {
+        assert_eq!(8u8, (w!(8#5) + w!(8#3)).into());
+        assert_eq!(0x100u16, (w!(16#0xFF) + w!(16#1)).into());
+        assert_eq!(4u8, (w!(3#7) + w!(3#5)).into());
+    }
+
+    #[test]
+    fn sub() {
+        assert_eq!(2u8, (w!(8#5) - w!(8#3)).into());
+        assert_eq!(0u8, (w!(8#255) - w!(8#255)).into());
+    }
+
+    #[test]
+    fn sub_borrows_across_bytes() {
+        assert_eq!(255u16, (w!(16#256) - w!(16#1)).into());
+        assert_eq!(0xFFFFu32, (w!(24#0x01_0000) - w!(24#1)).into());
+    }
+
+    #[test]
+    fn sub_wraps() {
+        assert_eq!(254u8, (w!(8#3) - w!(8#5)).into());
+        assert_eq!(14u8, (w!(4#3) - w!(4#5)).into());
+        assert_eq!(0x1FFFu16, (w!(13#0) - w!(13#1)).into());
+    }
+
+    #[test]
+    fn bitwise() {
+        assert_eq!(0b0100u8, (w!(4#0b1100) & w!(4#0b0110)).into());
+        assert_eq!(0b1110u8, (w!(4#0b1100) | w!(4#0b0110)).into());
+        assert_eq!(0b1010u8, (w!(4#0b1100) ^ w!(4#0b0110)).into());
+        assert_eq!(0xF0F0u16, (w!(16#0xFF00) ^ w!(16#0x0FF0)).into());
+    }
+
+    #[test]
+    fn not_stays_in_the_wire() {
+        assert_eq!(0b0011u8, (!w!(4#0b1100)).into());
+        assert_eq!(0x1FFFu16, (!w!(13#0)).into());
+        assert_eq!(0u8, (!w!(8#255)).into());
+    }
+}
@@ -0,0 +1,98 @@
+//! Zero and sign extension.
+
+use super::{byte_and_offset, BitCountType, Wire};
+
+/// Widening conversions into the wire type `W`.
+pub trait Extend<W> {
+    /// The number of bits we're extending from.
+    const FROM: BitCountType;
+    /// The number of bits we're extending to.
+    const TO: BitCountType;
+
+    /// Pads with zeros.
+    fn zext(self) -> W;
+
+    /// Pads with copies of the most significant bit.
+    fn sext(self) -> W;
+}
+
+// We can't constrain `N >= B` here (no numeric bounds on const generics yet) so
+// narrowing "extensions" are caught at runtime instead. The op nodes that use
+// this check when they're constructed, but anything else can call these too so
+// the check stays in release builds.
+impl<const B: BitCountType, const S: usize, const N: BitCountType, const M: usize>
+    Extend<Wire<{ N }, { M }>> for Wire<{ B }, { S }>
+{
+    const FROM: BitCountType = B;
+    const TO: BitCountType = N;
+
+    fn zext(self) -> Wire<{ N }, { M }> {
+        assert!(N >= B, "can't extend a {} bit wire to {} bits!", B, N);
+
+        let mut wire = Wire::<{ N }, { M }>::new();
+        wire.repr[0..S].copy_from_slice(&self.repr);
+
+        wire
+    }
+
+    fn sext(self) -> Wire<{ N }, { M }> {
+        let mut wire: Wire<{ N }, { M }> = self.zext();
+
+        if B == 0 {
+            return wire;
+        }
+
+        let (byte, offset) = byte_and_offset(B - 1);
+        if self.repr[byte] & (1 << offset) != 0 {
+            (B..N).for_each(|bit| {
+                let (byte, offset) = byte_and_offset(bit);
+                wire.repr[byte] |= 1 << offset;
+            });
+        }
+
+        wire
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wires::num_bytes;
+    use crate::w;
+
+    type W16 = Wire<{ 16 }, { num_bytes(16) }>;
+    type W20 = Wire<{ 20 }, { num_bytes(20) }>;
+
+    #[test]
+    fn zext() {
+        let w: W16 = w!(5#0b10110).zext();
+        assert_eq!(0b10110u16, w.into());
+
+        let w: W20 = w!(16#0xFFFF).zext();
+        assert_eq!(0xFFFFu32, w.into());
+    }
+
+    #[test]
+    fn sext() {
+        let w: W16 = w!(5#0b10110).sext();
+        assert_eq!(0xFFF6u16, w.into());
+
+        let w: W16 = w!(5#0b00110).sext();
+        assert_eq!(0b00110u16, w.into());
+
+        let w: W20 = w!(16#0x8000).sext();
+        assert_eq!(0xF_8000u32, w.into());
+    }
+
+    #[test]
+    #[should_panic]
+    fn narrowing() {
+        let _: Wire<{ 4 }, { 1 }> = w!(8#0x80).sext();
+    }
+
+    #[test]
+    fn same_width() {
+        let w: W16 = w!(16#0x8000).sext();
+        assert_eq!(0x8000u16, w.into());
+    }
+}
@@ -5,7 +5,9 @@ use core::ops::Index;
 use crate::util::ConstU8Arr;
 use core::slice::SliceIndex;
 
-use super::{BitCountType, Wire};
+use super::{byte_and_offset, BitCountType, Wire};
+
+use core::fmt::{self, Binary, Debug, Display, LowerHex, UpperHex};
 
 
 /// An array of ASCII characters (`u8`s) containing the formatted representation
@@ -110,7 +112,109 @@ impl</*'a, */const B: BitCountType, const S: usize> Fmt/*<'a>*/ for Wire<{ B },
     }
 }
 
+// The `core::fmt` traits. These are Verilog flavored: `Display` gives a sized
+// hex literal (`8'h05`, `12'hfff`) and the others print every digit of the
+// wire (so leading zeros are kept).
+
+impl<const B: BitCountType, const S: usize> Wire<{ B }, { S }> {
+    /// Hex digit `idx` (0 is the least significant).
+    fn nibble(&self, idx: usize) -> u8 {
+        (self.repr[idx / 2] >> (4 * (idx % 2))) & 0xF
+    }
+
+    /// The number of hex digits it takes to show every bit of the wire.
+    const fn hex_digits() -> usize {
+        (B + 3) / 4
+    }
 
-// use core::fmt::Debug;
+    fn fmt_hex(&self, f: &mut fmt::Formatter<'_>, upper: bool) -> fmt::Result {
+        if Self::hex_digits() == 0 {
+            return f.write_str("0");
+        }
+
+        for idx in (0..Self::hex_digits()).rev() {
+            if upper {
+                write!(f, "{:X}", self.nibble(idx))?;
+            } else {
+                write!(f, "{:x}", self.nibble(idx))?;
+            }
+        }
+
+        Ok(())
+    }
+}
 
-// Debug, Display, Binary, Octal, LowerHex, UpperHex
+/// `8'h05`; the same as [`Bits::literal`](../bits/struct.Bits.html#method.literal)
+/// but for wires of any width.
+impl<const B: BitCountType, const S: usize> Display for Wire<{ B }, { S }> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}'h", B)?;
+        self.fmt_hex(f, false)
+    }
+}
+
+impl<const B: BitCountType, const S: usize> Debug for Wire<{ B }, { S }> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl<const B: BitCountType, const S: usize> LowerHex for Wire<{ B }, { S }> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+
+        self.fmt_hex(f, false)
+    }
+}
+
+impl<const B: BitCountType, const S: usize> UpperHex for Wire<{ B }, { S }> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+
+        self.fmt_hex(f, true)
+    }
+}
+
+impl<const B: BitCountType, const S: usize> Binary for Wire<{ B }, { S }> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0b")?;
+        }
+
+        if B == 0 {
+            return f.write_str("0");
+        }
+
+        for bit in (0..B).rev() {
+            let (byte, offset) = byte_and_offset(bit);
+            f.write_str(if (self.repr[byte] >> offset) & 1 == 1 { "1" } else { "0" })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::w;
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", w!(8#5)), "8'h05");
+        assert_eq!(format!("{}", w!(12#0xFFF)), "12'hfff");
+        assert_eq!(format!("{}", w!(3#5)), "3'h5");
+        assert_eq!(format!("{}", w!(0#0)), "0'h0");
+    }
+
+    #[test]
+    fn other_formats() {
+        assert_eq!(format!("{:x}", w!(16#0xBEE)), "0bee");
+        assert_eq!(format!("{:#X}", w!(16#0xBEE)), "0x0BEE");
+        assert_eq!(format!("{:b}", w!(5#0b101)), "00101");
+        assert_eq!(format!("{:?}", w!(8#255)), "8'hff");
+    }
+}
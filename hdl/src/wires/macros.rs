@@ -43,10 +43,11 @@ macro_rules! new_wire_with_val {
 /// The type of the expression can be omitted. If not explicitly specified, the
 /// type will default to `usize`.
 ///
-/// When `num bits` is an expression it must be surrounded by `[]`; otherwise
-/// the square brackets can be omitted. The same goes for values: for literals
-/// the braces (`{}`) aren't required. Additionally when the type is omitted,
-/// braces on value expressions can be omitted.
+/// Square brackets around `num bits` and braces around `value` are always
+/// optional (in the `=>` form the number of bits is wrapped in braces rather
+/// than brackets). The one exception: a value expression that itself contains
+/// the token that introduces the type (`as` for the `for` form and `:` for the
+/// other two) has to be wrapped in braces so we can tell where it ends.
 ///
 /// There's also a shorthand for make new wires that aren't set to a value:
 ///  - `[expression for num bits] bits` or
@@ -70,7 +71,8 @@ macro_rules! new_wire_with_val {
 ///
 /// Fully qualified `=>` form:
 /// ```rust
-/// w!(u8:67 * 2 => )
+/// w!(u8:67 * 2 => 8);
+/// w!(u8:{67 * 2} => {4 + 4});
 /// ```
 ///
 /// Types can be omitted:
@@ -87,9 +89,11 @@ macro_rules! new_wire_with_val {
 /// w!([0 + 2 + 4]#6 + 8);
 /// ```
 ///
-/// Brackets for literal bit numbers can be omitted:
+/// Brackets for bit numbers can be omitted:
 /// ```rust
 /// w!(13 bits for {13 * 2} as u32);
+/// w!(3 * 3 bits for 3 * 2 as u32);
+/// w!(9 - 2#30 - 2 * 10:u128);
 /// ```
 ///
 ///
@@ -103,6 +107,9 @@ macro_rules! new_wire_with_val {
 /// ```rust
 /// let w = w!(9000 bits);
 /// ```
+/// ```rust
+/// let w = w!(<200 - 100>);
+/// ```
 
 // Matrix:
 //  - form: { for, hash, arrow }
@@ -116,37 +123,37 @@ macro_rules! new_wire_with_val {
 // │ for  │  "e"  │ "e" │ omit ┃ x │ // w!([3 * 3] bits for {3 * 2});
 // │ for  │  "e"  │ "e" │  yes ┃ x │ // w!([3 * 3] bits for {3 * 2} as u32);
 // │ for  │  "e"  │  e  │ omit ┃ x │ // w!([3 * 3] bits for 3 * 2);
-// │ for  │  "e"  │  e  │  yes ┃ - │ // w!([3 * 3] bits for 3 * 2 as u32);
+// │ for  │  "e"  │  e  │  yes ┃ x │ // w!([3 * 3] bits for 3 * 2 as u32);
 // │ for  │  "e"  │  l  │ omit ┃ x │ // w!([3 * 3] bits for 6);
 // │ for  │  "e"  │  l  │  yes ┃ x │ // w!([3 * 3] bits for 6 as u32);
-// │ for  │   e   │ "e" │ omit ┃ - │ // w!(3 * 3 bits for {3 * 2});
-// │ for  │   e   │ "e" │  yes ┃ - │ // w!(3 * 3 bits for {3 * 2} as u32);
-// │ for  │   e   │  e  │ omit ┃ - │ // w!(3 * 3 bits for 3 * 2);
-// │ for  │   e   │  e  │  yes ┃ - │ // w!(3 * 3 bits for 3 * 2 as u32);
-// │ for  │   e   │  l  │ omit ┃ - │ // w!(3 * 3 bits for 6);
-// │ for  │   e   │  l  │  yes ┃ - │ // w!(3 * 3 bits for 6 as u32);
+// │ for  │   e   │ "e" │ omit ┃ x │ // w!(3 * 3 bits for {3 * 2});
+// │ for  │   e   │ "e" │  yes ┃ x │ // w!(3 * 3 bits for {3 * 2} as u32);
+// │ for  │   e   │  e  │ omit ┃ x │ // w!(3 * 3 bits for 3 * 2);
+// │ for  │   e   │  e  │  yes ┃ x │ // w!(3 * 3 bits for 3 * 2 as u32);
+// │ for  │   e   │  l  │ omit ┃ x │ // w!(3 * 3 bits for 6);
+// │ for  │   e   │  l  │  yes ┃ x │ // w!(3 * 3 bits for 6 as u32);
 // │ for  │   l   │ "e" │ omit ┃ x │ // w!(9 bits for {3 * 2});
 // │ for  │   l   │ "e" │  yes ┃ x │ // w!(9 bits for {3 * 2} as u32);
 // │ for  │   l   │  e  │ omit ┃ x │ // w!(9 bits for 3 * 2);
 // │ for  │   l   │  e  │  yes ┃ x │ // w!(9 bits for 3 * 2 as u32);
-// │ for  │   l   │  l  │ omit ┃ - │ // w!(9 bits for 6);
+// │ for  │   l   │  l  │ omit ┃ x │ // w!(9 bits for 6);
 // │ for  │   l   │  l  │  yes ┃ x │ // w!(9 bits for 6 as u32);
 // │  #   │  "e"  │ "e" │ omit ┃ x │ // w!([9 - 2]#{30 - 2 * 10});
 // │  #   │  "e"  │ "e" │  yes ┃ x │ // w!([9 - 2]#{30 - 2 * 10}:u128);
 // │  #   │  "e"  │  e  │ omit ┃ x │ // w!([9 - 2]#30 - 2 * 10);
-// │  #   │  "e"  │  e  │  yes ┃ - │ // w!([9 - 2]#30 - 2 * 10:u128);
+// │  #   │  "e"  │  e  │  yes ┃ x │ // w!([9 - 2]#30 - 2 * 10:u128);
 // │  #   │  "e"  │  l  │ omit ┃ x │ // w!([9 - 2]#10);
 // │  #   │  "e"  │  l  │  yes ┃ x │ // w!([9 - 2]#10:u128);
-// │  #   │   e   │ "e" │ omit ┃ - │ // w!(9 - 2#{30 - 2 * 10});
-// │  #   │   e   │ "e" │  yes ┃ - │ // w!(9 - 2#{30 - 2 * 10}:u128);
-// │  #   │   e   │  e  │ omit ┃ - │ // w!(9 - 2#30 - 2 * 10);
-// │  #   │   e   │  e  │  yes ┃ - │ // w!(9 - 2#30 - 2 * 10:u128);
-// │  #   │   e   │  l  │ omit ┃ - │ // w!(9 - 2#10);
-// │  #   │   e   │  l  │  yes ┃ - │ // w!(9 - 2#10:u128);
+// │  #   │   e   │ "e" │ omit ┃ x │ // w!(9 - 2#{30 - 2 * 10});
+// │  #   │   e   │ "e" │  yes ┃ x │ // w!(9 - 2#{30 - 2 * 10}:u128);
+// │  #   │   e   │  e  │ omit ┃ x │ // w!(9 - 2#30 - 2 * 10);
+// │  #   │   e   │  e  │  yes ┃ x │ // w!(9 - 2#30 - 2 * 10:u128);
+// │  #   │   e   │  l  │ omit ┃ x │ // w!(9 - 2#10);
+// │  #   │   e   │  l  │  yes ┃ x │ // w!(9 - 2#10:u128);
 // │  #   │   l   │ "e" │ omit ┃ x │ // w!(7#{30 - 2 * 10});
 // │  #   │   l   │ "e" │  yes ┃ x │ // w!(7#{30 - 2 * 10}:u128);
 // │  #   │   l   │  e  │ omit ┃ x │ // w!(7#30 - 2 * 10);
-// │  #   │   l   │  e  │  yes ┃ - │ // w!(7#30 - 2 * 10:u128);
+// │  #   │   l   │  e  │  yes ┃ x │ // w!(7#30 - 2 * 10:u128);
 // │  #   │   l   │  l  │ omit ┃ x │ // w!(7#10);
 // │  #   │   l   │  l  │  yes ┃ x │ // w!(7#10:u128);
 // │  =>  │  "e"  │ "e" │ omit ┃ x │ // w!({20 * 2 - 1} => { 2 * 2 + 4 });
@@ -168,6 +175,15 @@ macro_rules! new_wire_with_val {
 // │  =>  │   l   │  l  │ omit ┃ x │ // w!(39 => 8);
 // │  =>  │   l   │  l  │  yes ┃ x │ // w!(u8: 39 => 8);
 // └──────┴───────┴─────┴──────┸───┘
+//
+// Since `expr` fragments can only be followed by `=>`, `,` or `;`, we can't
+// match most of the forms above with fragment specifiers. Instead we munch
+// tokens one at a time until we hit the token that separates the parts of the
+// form (`bits`, `#`, `=>`, then `as` or `:` for the type) and only glue the
+// tokens back together into expressions once we've split everything up.
+//
+// (`::` is a single token as far as `macro_rules!` is concerned so paths in
+// values and types don't trip up the `:` munching.)
 
 /// ```rust
 /// w!(0 bits for 0);
@@ -175,38 +191,138 @@ macro_rules! new_wire_with_val {
 ///
 #[macro_export(crate)]
 macro_rules! w {
-    // val expr + bits expr + type
-    ([$bits:expr] bits for { $val:expr } as $type:ty) => { $crate::new_wire_with_val!($bits, ($val) as $type) };
-    ([$bits:expr]#{$val:expr}:$type:ty) => { $crate::w!([$bits] bits for { $val } as $type) };
-    ($type:ty: $val:expr => $bits:expr) => { $crate::w!([$bits] bits for { $val } as $type) };
+    // Split on the first `bits`, `#` or `=>`. Everything before it is the
+    // number of bits (or, for the `=>` form, the type and the value).
+    (@split [$($head:tt)*] bits for $($val:tt)+) => { $crate::w!(@for [$($head)*] [] $($val)+) };
+    (@split [$($head:tt)*] bits) => { $crate::w!(@new $($head)*) };
+    (@split [$($head:tt)*] # $($val:tt)+) => { $crate::w!(@hash [$($head)*] [] $($val)+) };
+    (@split [$($head:tt)*] => $($bits:tt)+) => { $crate::w!(@arrow [] [$($bits)+] $($head)*) };
+    (@split [$($head:tt)*] $next:tt $($rest:tt)*) => { $crate::w!(@split [$($head)* $next] $($rest)*) };
+    (@split [$($head:tt)*]) => { $crate::w!(@new $($head)*) };
+
+    // `for` form: the value runs until `as` (if there is one).
+    (@for [$($bits:tt)+] [$($val:tt)+] as $($type:tt)+) => { $crate::w!(@make [$($bits)+] [$($val)+] [$($type)+]) };
+    (@for [$($bits:tt)+] [$($val:tt)*] $next:tt $($rest:tt)*) => { $crate::w!(@for [$($bits)+] [$($val)* $next] $($rest)*) };
+    (@for [$($bits:tt)+] [$($val:tt)+]) => { $crate::w!(@make [$($bits)+] [$($val)+] [usize]) };
 
-    // val expr + bits expr
-    ([$bits:expr] bits for $val:expr) => { $crate::w!([$bits] bits for { $val } as usize) };
-    ([$bits:expr]#{$val:expr}:$type:ty) => { $crate::w!([$bits] bits for $val) };
-    ($val:expr => $bits:expr) => { $crate::w!(($bits) bits for { $val } as $type) };
+    // `#` form: the value runs until `:` (if there is one).
+    (@hash [$($bits:tt)+] [$($val:tt)+] : $($type:tt)+) => { $crate::w!(@make [$($bits)+] [$($val)+] [$($type)+]) };
+    (@hash [$($bits:tt)+] [$($val:tt)*] $next:tt $($rest:tt)*) => { $crate::w!(@hash [$($bits)+] [$($val)* $next] $($rest)*) };
+    (@hash [$($bits:tt)+] [$($val:tt)+]) => { $crate::w!(@make [$($bits)+] [$($val)+] [usize]) };
 
-    // val expr + type
-    ($bits:literal bits for $val:expr) => { $crate::w!([$bits] bits for $val) };
+    // `=>` form: the type (if there is one) runs until `:`.
+    (@arrow [$($type:tt)+] [$($bits:tt)+] : $($val:tt)+) => { $crate::w!(@make [$($bits)+] [$($val)+] [$($type)+]) };
+    (@arrow [$($head:tt)*] [$($bits:tt)+] $next:tt $($rest:tt)*) => { $crate::w!(@arrow [$($head)* $next] [$($bits)+] $($rest)*) };
+    (@arrow [$($val:tt)+] [$($bits:tt)+]) => { $crate::w!(@make [$($bits)+] [$($val)+] [usize]) };
 
+    // `<num bits>` form: the number of bits runs until the closing `>`.
+    (@angle [$($bits:tt)+] >) => { $crate::w!(@new $($bits)+) };
+    (@angle [$($bits:tt)*] $next:tt $($rest:tt)*) => { $crate::w!(@angle [$($bits)* $next] $($rest)*) };
+
+    // Strip the (optional) square brackets around the number of bits and put
+    // everything back together.
+    (@make [[$($bits:tt)+]] [$($val:tt)+] [$($type:tt)+]) => { $crate::new_wire_with_val!($($bits)+, ($($val)+) as $($type)+) };
+    (@make [$($bits:tt)+] [$($val:tt)+] [$($type:tt)+]) => { $crate::new_wire_with_val!($($bits)+, ($($val)+) as $($type)+) };
+
+    (@new [$($bits:tt)+]) => { $crate::new_wire!($($bits)+) };
+    (@new $($bits:tt)+) => { $crate::new_wire!($($bits)+) };
+
+    (< $($rest:tt)+) => { $crate::w!(@angle [] $($rest)+) };
+    ($($tokens:tt)+) => { $crate::w!(@split [] $($tokens)+) };
+}
 
-    // bits expr + type
+#[cfg(test)]
+mod tests {
+    use crate::wires::{num_bytes, Wire};
 
-    // val expr
+    // One test per row of the matrix above; each checks the number of bits (by
+    // way of the type annotation) and the value.
+    macro_rules! matrix {
+        ($($name:ident: w!($($form:tt)+) == $bits:literal bits of $val:literal;)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let w: Wire<{ $bits }, { num_bytes($bits) }> = w!($($form)+);
+                    assert_eq!($val, u128::from(w));
+                }
+            )*
+        };
+    }
 
-    // bits expr
+    matrix! {
+        for_qe_qe_omit: w!([3 * 3] bits for {3 * 2}) == 9 bits of 6;
+        for_qe_qe_type: w!([3 * 3] bits for {3 * 2} as u32) == 9 bits of 6;
+        for_qe_e_omit: w!([3 * 3] bits for 3 * 2) == 9 bits of 6;
+        for_qe_e_type: w!([3 * 3] bits for 3 * 2 as u32) == 9 bits of 6;
+        for_qe_l_omit: w!([3 * 3] bits for 6) == 9 bits of 6;
+        for_qe_l_type: w!([3 * 3] bits for 6 as u32) == 9 bits of 6;
+        for_e_qe_omit: w!(3 * 3 bits for {3 * 2}) == 9 bits of 6;
+        for_e_qe_type: w!(3 * 3 bits for {3 * 2} as u32) == 9 bits of 6;
+        for_e_e_omit: w!(3 * 3 bits for 3 * 2) == 9 bits of 6;
+        for_e_e_type: w!(3 * 3 bits for 3 * 2 as u32) == 9 bits of 6;
+        for_e_l_omit: w!(3 * 3 bits for 6) == 9 bits of 6;
+        for_e_l_type: w!(3 * 3 bits for 6 as u32) == 9 bits of 6;
+        for_l_qe_omit: w!(9 bits for {3 * 2}) == 9 bits of 6;
+        for_l_qe_type: w!(9 bits for {3 * 2} as u32) == 9 bits of 6;
+        for_l_e_omit: w!(9 bits for 3 * 2) == 9 bits of 6;
+        for_l_e_type: w!(9 bits for 3 * 2 as u32) == 9 bits of 6;
+        for_l_l_omit: w!(9 bits for 6) == 9 bits of 6;
+        for_l_l_type: w!(9 bits for 6 as u32) == 9 bits of 6;
 
-    // nothing
+        hash_qe_qe_omit: w!([9 - 2]#{30 - 2 * 10}) == 7 bits of 10;
+        hash_qe_qe_type: w!([9 - 2]#{30 - 2 * 10}:u128) == 7 bits of 10;
+        hash_qe_e_omit: w!([9 - 2]#30 - 2 * 10) == 7 bits of 10;
+        hash_qe_e_type: w!([9 - 2]#30 - 2 * 10:u128) == 7 bits of 10;
+        hash_qe_l_omit: w!([9 - 2]#10) == 7 bits of 10;
+        hash_qe_l_type: w!([9 - 2]#10:u128) == 7 bits of 10;
+        hash_e_qe_omit: w!(9 - 2#{30 - 2 * 10}) == 7 bits of 10;
+        hash_e_qe_type: w!(9 - 2#{30 - 2 * 10}:u128) == 7 bits of 10;
+        hash_e_e_omit: w!(9 - 2#30 - 2 * 10) == 7 bits of 10;
+        hash_e_e_type: w!(9 - 2#30 - 2 * 10:u128) == 7 bits of 10;
+        hash_e_l_omit: w!(9 - 2#10) == 7 bits of 10;
+        hash_e_l_type: w!(9 - 2#10:u128) == 7 bits of 10;
+        hash_l_qe_omit: w!(7#{30 - 2 * 10}) == 7 bits of 10;
+        hash_l_qe_type: w!(7#{30 - 2 * 10}:u128) == 7 bits of 10;
+        hash_l_e_omit: w!(7#30 - 2 * 10) == 7 bits of 10;
+        hash_l_e_type: w!(7#30 - 2 * 10:u128) == 7 bits of 10;
+        hash_l_l_omit: w!(7#10) == 7 bits of 10;
+        hash_l_l_type: w!(7#10:u128) == 7 bits of 10;
 
+        arrow_qe_qe_omit: w!({20 * 2 - 1} => { 2 * 2 + 4 }) == 8 bits of 39;
+        arrow_qe_qe_type: w!(u8:{20 * 2 - 1} => { 2 * 2 + 4 }) == 8 bits of 39;
+        arrow_qe_e_omit: w!(20 * 2 - 1 => { 2 * 2 + 4 }) == 8 bits of 39;
+        arrow_qe_e_type: w!(u8: 20 * 2 - 1 => { 2 * 2 + 4 }) == 8 bits of 39;
+        arrow_qe_l_omit: w!(39 => { 2 * 2 + 4 }) == 8 bits of 39;
+        arrow_qe_l_type: w!(u8: 39 => { 2 * 2 + 4 }) == 8 bits of 39;
+        arrow_e_qe_omit: w!({20 * 2 - 1} => 2 * 2 + 4) == 8 bits of 39;
+        arrow_e_qe_type: w!(u8:{20 * 2 - 1} => 2 * 2 + 4) == 8 bits of 39;
+        arrow_e_e_omit: w!(20 * 2 - 1 => 2 * 2 + 4) == 8 bits of 39;
+        arrow_e_e_type: w!(u8: 20 * 2 - 1 => 2 * 2 + 4) == 8 bits of 39;
+        arrow_e_l_omit: w!(39 => 2 * 2 + 4) == 8 bits of 39;
+        arrow_e_l_type: w!(u8: 39 => 2 * 2 + 4) == 8 bits of 39;
+        arrow_l_qe_omit: w!({20 * 2 - 1} => 8) == 8 bits of 39;
+        arrow_l_qe_type: w!(u8:{20 * 2 - 1} => 8) == 8 bits of 39;
+        arrow_l_e_omit: w!(20 * 2 - 1 => 8) == 8 bits of 39;
+        arrow_l_e_type: w!(u8: 20 * 2 - 1 => 8) == 8 bits of 39;
+        arrow_l_l_omit: w!(39 => 8) == 8 bits of 39;
+        arrow_l_l_type: w!(u8: 39 => 8) == 8 bits of 39;
+    }
 
-    ($bits:literal bits for $val:expr) => { crate::w!(($bits) bits for $val) };
+    #[test]
+    fn paths_in_values_and_types() {
+        let w: Wire<{ 64 }, { num_bytes(64) }> = w!([8 * 8] bits for {core::u64::MAX} as u64);
+        assert_eq!(core::u64::MAX, u64::from(w));
 
-    ($val:expr => ($bits:expr) bits) => { crate::w!(($bits) bits for $val) };
-    ($val:expr => $bits:literal bits) => { crate::w!($bits bits for $val) };
-    (($bits:expr)#$val:expr) => { crate::w!(($bits) bits for $val) };
-    ($bits:literal#$val:expr) => { crate::w!($bits bits for $val) };
+        let w: Wire<{ 64 }, { num_bytes(64) }> = w!(64#core::u64::MAX:u64);
+        assert_eq!(core::u64::MAX, u64::from(w));
+    }
 
-    // new wire
-    ([$bits:expr] bits) => { crate::new_wire!($bits) };
-    ($bits:literal bits) => { crate::w!([$bits] bits) };
-    ($bits:expr) => { crate::w!([$bits] bits) };
+    #[test]
+    fn new_wires() {
+        let _: Wire<{ 100 }, { num_bytes(100) }> = w!([200 - 100] bits);
+        let _: Wire<{ 100 }, { num_bytes(100) }> = w!(200 - 100 bits);
+        let _: Wire<{ 100 }, { num_bytes(100) }> = w!(100 bits);
+        let _: Wire<{ 100 }, { num_bytes(100) }> = w!(<200 - 100>);
+        let _: Wire<{ 35 }, { num_bytes(35) }> = w!(34 + 1);
+    }
 }
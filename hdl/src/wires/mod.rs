@@ -8,15 +8,21 @@
 //! aligned -- depending on the architecture -- so that each element has a
 //! unique address).
 
+mod arith;
 mod conversions;
+mod extend;
 mod fmt;
 mod index;
 mod macros;
+mod width;
 
 use core::mem::{size_of, MaybeUninit};
 
 use crate::util::ConstU8Arr;
 use conversions::IntoBits;
+pub use conversions::{FitsInU8, FitsInU16, FitsInU32, FitsInU64, FitsInU128};
+pub use extend::Extend;
+pub use width::{Width, WireOfWidth};
 use core::convert::TryInto;
 
 /// The type used to count the number of bits a wire contains.
@@ -0,0 +1,36 @@
+//! Going from a number of bits to the `Wire` type with that many bits.
+//!
+//! `Wire` takes both the number of bits _and_ the number of bytes as const
+//! generic parameters since we can't (yet) compute the number of bytes from the
+//! number of bits in type position when the number of bits is itself a generic
+//! parameter (see the commented out `WireAlias` in `mod.rs`). Anything that
+//! wants to produce a wire of some caller-chosen width (i.e. `zext::<16>`)
+//! runs right into this.
+//!
+//! So, same as with the `FitsInU*` traits, we spell the mapping out for every
+//! width we care about.
+
+use super::{num_bytes, BitCountType, Wire};
+use crate::bits::FromBits;
+use core::fmt::Display;
+use repeat_macros::repeat_with_n;
+
+/// A number of bits, lifted into the type system.
+#[derive(Copy, Clone, Debug)]
+pub struct Width<const B: BitCountType>;
+
+/// Maps a [`Width`](struct.Width.html) to the corresponding `Wire` type.
+pub trait WireOfWidth {
+    /// The `Wire` type with this many bits.
+    type Wire: Copy + Display + FromBits;
+}
+
+macro_rules! width_impl {
+    ($num:expr) => {
+        impl WireOfWidth for Width<{ $num as BitCountType }> {
+            type Wire = Wire<{ $num as BitCountType }, { num_bytes($num as BitCountType) }>;
+        }
+    };
+}
+
+repeat_with_n!(128, N, width_impl! {N});